@@ -10,6 +10,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     pool::{ConcentratedLiquidity, UniPool},
     sol_types::PoolKey,
+    v3_base::{
+        depth::{self, DepthLevel},
+        distribution::LiquidityDistribution,
+    },
     v2_pool::V2Pool,
     v3_pool::V3Pool,
     v4_pool::V4Pool,
@@ -22,6 +26,36 @@ pub enum AnyPool<P: Provider> {
 }
 
 impl<P: Provider> AnyPool<P> {
+    /// Syncs the state, and the ticks of concentrated pools when `ticks` is set
+    pub async fn resync(&mut self, ticks: bool) -> Result<(), ()> {
+        if ticks {
+            return self.super_sync().await;
+        }
+        match self {
+            AnyPool::V2(v2_pool) => v2_pool.sync().await,
+            AnyPool::V3(v3_pool) => v3_pool.sync().await,
+            AnyPool::V4(v4_pool) => v4_pool.sync().await,
+        }
+    }
+
+    /// Depth on both sides of the price at every move in `levels`, in basis points
+    pub fn depth_ladder(&self, levels: &[u32]) -> Option<Vec<DepthLevel>> {
+        match self {
+            AnyPool::V2(v2_pool) => v2_pool.state.depth_ladder(levels),
+            AnyPool::V3(v3_pool) => depth::depth_ladder(&v3_pool.state, levels),
+            AnyPool::V4(v4_pool) => depth::depth_ladder(&v4_pool.state, levels),
+        }
+    }
+
+    /// Active liquidity between initialized ticks, `None` for V2 pools
+    pub fn liquidity_distribution(&self) -> Option<LiquidityDistribution> {
+        match self {
+            AnyPool::V2(_) => None,
+            AnyPool::V3(v3_pool) => LiquidityDistribution::from_state(&v3_pool.state),
+            AnyPool::V4(v4_pool) => LiquidityDistribution::from_state(&v4_pool.state),
+        }
+    }
+
     pub async fn super_sync(&mut self) -> Result<(), ()> {
         match self {
             AnyPool::V2(v2_pool) => v2_pool.sync().await,
@@ -49,7 +83,7 @@ impl<P: Provider> UniPool for AnyPool<P> {
         &mut self,
         amount: alloy::primitives::U256,
         from0: bool,
-    ) -> Result<crate::any_trade::UniTrade, crate::err::TradeError> {
+    ) -> Result<crate::v3_base::states::TradeReceipt, crate::err::TradeError> {
         match self {
             AnyPool::V2(v2_pool) => v2_pool.trade(amount, from0),
             AnyPool::V3(v3_pool) => v3_pool.trade(amount, from0),
@@ -84,6 +118,52 @@ impl<P: Provider> UniPool for AnyPool<P> {
         }
     }
 
+    fn apply_trade(
+        &mut self,
+        receipt: &crate::v3_base::states::TradeReceipt,
+    ) -> Result<(), crate::err::StateError> {
+        match self {
+            Self::V2(v2_pool) => v2_pool.apply_trade(receipt),
+            Self::V3(v3_pool) => v3_pool.apply_trade(receipt),
+            Self::V4(v4_pool) => v4_pool.apply_trade(receipt),
+        }
+    }
+
+    fn sync_status(&self) -> &crate::pool::SyncStatus {
+        match self {
+            Self::V2(v2_pool) => v2_pool.sync_status(),
+            Self::V3(v3_pool) => v3_pool.sync_status(),
+            Self::V4(v4_pool) => v4_pool.sync_status(),
+        }
+    }
+
+    fn sync_status_mut(&mut self) -> &mut crate::pool::SyncStatus {
+        match self {
+            Self::V2(v2_pool) => v2_pool.sync_status_mut(),
+            Self::V3(v3_pool) => v3_pool.sync_status_mut(),
+            Self::V4(v4_pool) => v4_pool.sync_status_mut(),
+        }
+    }
+
+    fn checkpoint(&self) -> crate::pool::PoolCheckpoint {
+        match self {
+            Self::V2(v2_pool) => v2_pool.checkpoint(),
+            Self::V3(v3_pool) => v3_pool.checkpoint(),
+            Self::V4(v4_pool) => v4_pool.checkpoint(),
+        }
+    }
+
+    fn rollback(
+        &mut self,
+        checkpoint: crate::pool::PoolCheckpoint,
+    ) -> Result<(), crate::err::StateError> {
+        match self {
+            Self::V2(v2_pool) => v2_pool.rollback(checkpoint),
+            Self::V3(v3_pool) => v3_pool.rollback(checkpoint),
+            Self::V4(v4_pool) => v4_pool.rollback(checkpoint),
+        }
+    }
+
     fn get_a(&self) -> &Address {
         match self {
             Self::V2(v2_pool) => v2_pool.get_a(),
@@ -100,7 +180,7 @@ impl<P: Provider> UniPool for AnyPool<P> {
         }
     }
 
-    fn get_price(&self) -> alloy::primitives::U256 {
+    fn get_price(&self) -> crate::price::Price {
         match self {
             Self::V2(v2_pool) => v2_pool.get_price(),
             Self::V3(v3_pool) => v3_pool.get_price(),
@@ -117,7 +197,7 @@ impl<P: Provider> UniPool for AnyPool<P> {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct V4Key {
     pub currency0: Address,
     pub currency1: Address,
@@ -1,8 +1,93 @@
-use crate::{v2_base::V2Trade, v3_base::states::TradeState};
+use alloy::primitives::U256;
 
+use crate::{
+    price::Price,
+    v2_base::V2Trade,
+    v3_base::states::{TradeReceipt, TradeState},
+};
+
+/// Common view over a simulated swap
+pub trait Trade {
+    fn from0(&self) -> bool;
+    fn amount_in(&self) -> U256;
+    fn amount_out(&self) -> U256;
+    fn fee_amount(&self) -> U256;
+    fn price_before(&self) -> Price;
+    fn price_after(&self) -> Price;
+    fn ticks_crossed(&self) -> u32;
+
+    /// Average price paid, fee included, as token1 per token0 like the pool prices
+    fn execution_price(&self) -> Option<Price> {
+        if self.from0() {
+            Price::from_amounts(self.amount_in(), self.amount_out())
+        } else {
+            Price::from_amounts(self.amount_out(), self.amount_in())
+        }
+    }
+
+    /// How much worse the execution price is than the pre-trade price, as a
+    /// fraction. Includes the fee, so a tiny trade reports about the fee rate.
+    fn price_impact(&self) -> f64 {
+        let Some(execution) = self.execution_price() else {
+            return 0.0;
+        };
+        let before = self.price_before().to_f64();
+        let execution = execution.to_f64();
+        if before <= 0.0 || execution <= 0.0 {
+            return 0.0;
+        }
+        // selling token0 lowers the price, selling token1 raises it
+        if self.from0() {
+            1.0 - execution / before
+        } else {
+            1.0 - before / execution
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum UniTrade {
     V2(V2Trade),
     V3(TradeState),
+    V4(TradeState),
+}
+
+impl UniTrade {
+    pub fn amount_in(&self) -> U256 {
+        match self {
+            UniTrade::V2(v2_trade) => v2_trade.amount_in,
+            UniTrade::V3(trade_state) | UniTrade::V4(trade_state) => {
+                trade_state.amount_in
+            }
+        }
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        match self {
+            UniTrade::V2(v2_trade) => v2_trade.amount_out,
+            UniTrade::V3(trade_state) | UniTrade::V4(trade_state) => {
+                trade_state.amount_out
+            }
+        }
+    }
+
+    pub fn fee_amount(&self) -> U256 {
+        match self {
+            UniTrade::V2(v2_trade) => v2_trade.fee_amount,
+            UniTrade::V3(trade_state) | UniTrade::V4(trade_state) => {
+                trade_state.fee_amount
+            }
+        }
+    }
+
+    pub fn ticks_crossed(&self) -> u32 {
+        match self {
+            UniTrade::V2(_) => 0,
+            UniTrade::V3(trade_state) | UniTrade::V4(trade_state) => {
+                trade_state.ticks_crossed
+            }
+        }
+    }
 }
 
 impl From<TradeState> for UniTrade {
@@ -16,3 +101,33 @@ impl From<V2Trade> for UniTrade {
         Self::V2(value)
     }
 }
+
+impl Trade for TradeReceipt {
+    fn from0(&self) -> bool {
+        self.from0
+    }
+
+    fn amount_in(&self) -> U256 {
+        self.amount_in
+    }
+
+    fn amount_out(&self) -> U256 {
+        self.amount_out
+    }
+
+    fn fee_amount(&self) -> U256 {
+        self.fee_amount
+    }
+
+    fn price_before(&self) -> Price {
+        self.price_before
+    }
+
+    fn price_after(&self) -> Price {
+        self.price_after
+    }
+
+    fn ticks_crossed(&self) -> u32 {
+        self.ticks_crossed
+    }
+}
@@ -0,0 +1,34 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Progress marker for block range scans. `next_block` is the first block that was
+/// not processed yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub next_block: u64,
+}
+
+impl Checkpoint {
+    pub fn new(next_block: u64) -> Self {
+        Self {
+            next_block,
+        }
+    }
+
+    /// Returns `None` when the file does not exist or can't be parsed
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// Writes to a temporary file first so a crash never leaves a truncated checkpoint
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let data = serde_json::to_string(self).map_err(io::Error::other)?;
+
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+}
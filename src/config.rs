@@ -0,0 +1,323 @@
+use std::{fs, path::Path};
+
+use alloy::primitives::{address, Address, B256};
+use alloy_provider::Provider;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    err::ConfigError,
+    generate_fallback_provider,
+    multicall::MULTICALL3_ADDRESS,
+    pool_address::{
+        Create2Deployment,
+        PANCAKE_V2_BSC,
+        PANCAKE_V3_BSC,
+        UNISWAP_V2_ETHEREUM,
+        UNISWAP_V3_BSC,
+        UNISWAP_V3_ETHEREUM,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolKind {
+    UniswapV2,
+    UniswapV3,
+    UniswapV4,
+    Curve,
+}
+
+/// One DEX deployment on a chain. Fields that don't apply to the protocol kind are
+/// left empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DexConfig {
+    pub name: String,
+    pub kind: ProtocolKind,
+    #[serde(default)]
+    pub factory: Option<Address>,
+    /// CREATE2 deployer when it differs from the factory (PancakeSwap V3)
+    #[serde(default)]
+    pub pool_deployer: Option<Address>,
+    #[serde(default)]
+    pub init_code_hash: Option<B256>,
+    /// Fees in hundredths of a bip. V2 uses the first entry, V3 probes all of them.
+    #[serde(default)]
+    pub fees: Vec<u32>,
+    #[serde(default)]
+    pub state_view: Option<Address>,
+    #[serde(default)]
+    pub pool_manager: Option<Address>,
+    /// Curve meta registry
+    #[serde(default)]
+    pub registry: Option<Address>,
+    /// Swap router transactions are sent to
+    #[serde(default)]
+    pub router: Option<Address>,
+    /// QuoterV2 for V3 deployments
+    #[serde(default)]
+    pub quoter: Option<Address>,
+    /// First block worth scanning for this deployment's events
+    #[serde(default)]
+    pub deployment_block: u64,
+}
+
+impl DexConfig {
+    /// The CREATE2 parameters for offline pool address computation
+    pub fn create2(&self) -> Option<Create2Deployment> {
+        Some(Create2Deployment {
+            deployer: self.pool_deployer.or(self.factory)?,
+            init_code_hash: self.init_code_hash?,
+        })
+    }
+
+    /// Swap fee for V2 style deployments, 0.3% when not configured
+    pub fn v2_fee(&self) -> u32 {
+        self.fees.first().copied().unwrap_or(3000)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub rpc_urls: Vec<String>,
+    pub wrapped_native: Address,
+    #[serde(default = "default_multicall")]
+    pub multicall: Address,
+    #[serde(default)]
+    pub dexes: Vec<DexConfig>,
+}
+
+fn default_multicall() -> Address {
+    MULTICALL3_ADDRESS
+}
+
+impl ChainConfig {
+    /// Loads a `.toml` or `.json` file, picked by extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml_str(&data),
+            Some("json") => Self::from_json_str(&data),
+            _ => Err(ConfigError::UnknownFormat),
+        }
+    }
+
+    pub fn from_toml_str(data: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(data)?)
+    }
+
+    pub fn from_json_str(data: &str) -> Result<Self, ConfigError> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    pub fn dex(&self, name: &str) -> Option<&DexConfig> {
+        self.dexes.iter().find(|d| d.name == name)
+    }
+
+    pub fn dexes_of(&self, kind: ProtocolKind) -> impl Iterator<Item = &DexConfig> {
+        self.dexes.iter().filter(move |d| d.kind == kind)
+    }
+
+    /// Fallback provider over every configured RPC url
+    pub fn provider(&self) -> impl Provider + Clone {
+        generate_fallback_provider(self.rpc_urls.clone())
+    }
+
+    pub fn bsc() -> Self {
+        Self {
+            chain_id: 56,
+            rpc_urls: vec![
+                "https://binance.llamarpc.com".to_string(),
+                "https://bsc.rpc.blxrbdn.com".to_string(),
+                "https://bsc-mainnet.public.blastapi.io".to_string(),
+                "https://bsc.drpc.org".to_string(),
+            ],
+            wrapped_native: address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"),
+            multicall: MULTICALL3_ADDRESS,
+            dexes: vec![
+                DexConfig {
+                    name: "pancakeswap_v2".to_string(),
+                    kind: ProtocolKind::UniswapV2,
+                    factory: Some(PANCAKE_V2_BSC.deployer),
+                    pool_deployer: None,
+                    init_code_hash: Some(PANCAKE_V2_BSC.init_code_hash),
+                    fees: vec![2500],
+                    state_view: None,
+                    pool_manager: None,
+                    registry: None,
+                    router: Some(address!("10ED43C718714eb63d5aA57B78B54704E256024E")),
+                    quoter: None,
+                    deployment_block: 6_809_737,
+                },
+                DexConfig {
+                    name: "pancakeswap_v3".to_string(),
+                    kind: ProtocolKind::UniswapV3,
+                    factory: Some(address!("0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865")),
+                    pool_deployer: Some(PANCAKE_V3_BSC.deployer),
+                    init_code_hash: Some(PANCAKE_V3_BSC.init_code_hash),
+                    fees: vec![
+                        100, 500, 2500, 10000,
+                    ],
+                    state_view: None,
+                    pool_manager: None,
+                    registry: None,
+                    router: Some(address!("13f4EA83D0bd40E75C8222255bc855a974568Dd4")),
+                    quoter: Some(address!("B048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997")),
+                    deployment_block: 26_956_207,
+                },
+                DexConfig {
+                    name: "uniswap_v3".to_string(),
+                    kind: ProtocolKind::UniswapV3,
+                    factory: Some(UNISWAP_V3_BSC.deployer),
+                    pool_deployer: None,
+                    init_code_hash: Some(UNISWAP_V3_BSC.init_code_hash),
+                    fees: vec![
+                        100, 500, 3000, 10000,
+                    ],
+                    state_view: None,
+                    pool_manager: None,
+                    registry: None,
+                    router: Some(address!("B971eF87ede563556b2ED4b1C0b0019111Dd85d2")),
+                    quoter: Some(address!("78D78E420Da98ad378D7799bE8f4AF69033EB077")),
+                    deployment_block: 26_324_014,
+                },
+                DexConfig {
+                    name: "uniswap_v4".to_string(),
+                    kind: ProtocolKind::UniswapV4,
+                    factory: None,
+                    pool_deployer: None,
+                    init_code_hash: None,
+                    fees: Vec::new(),
+                    state_view: Some(address!(
+                        "d13Dd3D6E93f276FAfc9Db9E6BB47C1180aeE0c4"
+                    )),
+                    pool_manager: Some(address!(
+                        "28e2Ea090877bF75740558f6BFB36A5ffeE9e9dF"
+                    )),
+                    registry: None,
+                    router: Some(address!("1906c1d672b88cD1B9aC7593301cA990F94Eae07")),
+                    quoter: None,
+                    deployment_block: 45_000_000,
+                },
+            ],
+        }
+    }
+
+    pub fn ethereum() -> Self {
+        Self {
+            chain_id: 1,
+            rpc_urls: vec![
+                "https://eth.llamarpc.com".to_string(),
+                "https://ethereum-rpc.publicnode.com".to_string(),
+                "https://eth.drpc.org".to_string(),
+            ],
+            wrapped_native: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            multicall: MULTICALL3_ADDRESS,
+            dexes: vec![
+                DexConfig {
+                    name: "uniswap_v2".to_string(),
+                    kind: ProtocolKind::UniswapV2,
+                    factory: Some(UNISWAP_V2_ETHEREUM.deployer),
+                    pool_deployer: None,
+                    init_code_hash: Some(UNISWAP_V2_ETHEREUM.init_code_hash),
+                    fees: vec![3000],
+                    state_view: None,
+                    pool_manager: None,
+                    registry: None,
+                    router: Some(address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D")),
+                    quoter: None,
+                    deployment_block: 10_000_835,
+                },
+                DexConfig {
+                    name: "uniswap_v3".to_string(),
+                    kind: ProtocolKind::UniswapV3,
+                    factory: Some(UNISWAP_V3_ETHEREUM.deployer),
+                    pool_deployer: None,
+                    init_code_hash: Some(UNISWAP_V3_ETHEREUM.init_code_hash),
+                    fees: vec![
+                        100, 500, 3000, 10000,
+                    ],
+                    state_view: None,
+                    pool_manager: None,
+                    registry: None,
+                    router: Some(address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45")),
+                    quoter: Some(address!("61fFE014bA17989E743c5F6cB21bF9697530B21e")),
+                    deployment_block: 12_369_621,
+                },
+                DexConfig {
+                    name: "uniswap_v4".to_string(),
+                    kind: ProtocolKind::UniswapV4,
+                    factory: None,
+                    pool_deployer: None,
+                    init_code_hash: None,
+                    fees: Vec::new(),
+                    state_view: Some(address!(
+                        "7fFE42C4a5DEeA5b0feC41C94C136Cf115597227"
+                    )),
+                    pool_manager: Some(address!(
+                        "000000000004444c5dc75cB358380D2e3dE08A90"
+                    )),
+                    registry: None,
+                    router: Some(address!("66a9893cC07D91D95644AEDD05D03f95e1dBA8Af")),
+                    quoter: None,
+                    deployment_block: 21_688_329,
+                },
+                DexConfig {
+                    name: "curve".to_string(),
+                    kind: ProtocolKind::Curve,
+                    factory: None,
+                    pool_deployer: None,
+                    init_code_hash: None,
+                    fees: Vec::new(),
+                    state_view: None,
+                    pool_manager: None,
+                    registry: Some(address!("F98B45FA17DE75FB1aD0e7aFD971b0ca00e379fC")),
+                    router: None,
+                    quoter: None,
+                    deployment_block: 0,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_parse() {
+        for preset in [
+            ChainConfig::bsc(),
+            ChainConfig::ethereum(),
+        ] {
+            let toml = toml::to_string(&preset).unwrap();
+            assert_eq!(ChainConfig::from_toml_str(&toml).unwrap(), preset);
+            let json = serde_json::to_string(&preset).unwrap();
+            assert_eq!(ChainConfig::from_json_str(&json).unwrap(), preset);
+        }
+
+        let config = ChainConfig::from_toml_str(
+            r#"
+            chain_id = 56
+            rpc_urls = ["https://bsc-dataseed.bnbchain.org"]
+            wrapped_native = "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"
+
+            [[dexes]]
+            name = "pancake_v2"
+            kind = "uniswap_v2"
+            factory = "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.multicall, MULTICALL3_ADDRESS);
+        let dex = config.dex("pancake_v2").unwrap();
+        assert_eq!(dex.kind, ProtocolKind::UniswapV2);
+        assert_eq!(dex.v2_fee(), 3000);
+        assert_eq!(dex.deployment_block, 0);
+
+        assert!(ChainConfig::from_json_str("{\"chain_id\": 1}").is_err());
+    }
+}
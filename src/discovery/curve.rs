@@ -0,0 +1,505 @@
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, U256};
+use alloy_provider::Provider;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ChainConfig, ProtocolKind},
+    err::DiscoveryError,
+    multicall::{self, Multicall},
+    sol_types::{
+        ICurveFactory::{self, ICurveFactoryInstance},
+        ICurveMetaRegistry::{
+            self, get_coinsCall, get_n_coinsCall, get_underlying_coinsCall, is_metaCall,
+            ICurveMetaRegistryInstance,
+        },
+        ICurveV1PlainPool::coinsCall,
+        ICurveV1Underlying::base_poolCall,
+        ICurveV2CryptoPool::gammaCall,
+    },
+};
+
+/// Curve pools hold at most 8 coins
+const MAX_COINS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CurvePoolKind {
+    /// StableSwap pool trading its own coins
+    Plain,
+    /// StableSwap pool paired against a base pool LP token
+    Meta,
+    /// CryptoSwap pool with a dynamic peg (`gamma`, `price_scale`)
+    Crypto,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurvePoolInfo {
+    pub address: Address,
+    pub kind: CurvePoolKind,
+    /// Coins in pool index order, as used by `exchange`
+    pub coins: Vec<Address>,
+    /// Underlying coins in index order, as used by `exchange_underlying`.
+    /// Empty when the pool has no underlying set.
+    pub underlying_coins: Vec<Address>,
+}
+
+impl CurvePoolInfo {
+    pub fn coin_index(&self, token: Address) -> Option<i128> {
+        self.coins
+            .iter()
+            .position(|c| *c == token)
+            .map(|i| i as i128)
+    }
+
+    pub fn underlying_index(&self, token: Address) -> Option<i128> {
+        self.underlying_coins
+            .iter()
+            .position(|c| *c == token)
+            .map(|i| i as i128)
+    }
+
+    /// Mirrors the registry `get_coin_indices`: returns `(i, j, underlying)` where
+    /// `underlying` tells whether `exchange_underlying` has to be used.
+    pub fn coin_indices(&self, from: Address, to: Address) -> Option<(i128, i128, bool)> {
+        if let (Some(i), Some(j)) = (self.coin_index(from), self.coin_index(to)) {
+            return Some((i, j, false));
+        }
+        if let (Some(i), Some(j)) =
+            (self.underlying_index(from), self.underlying_index(to))
+        {
+            return Some((i, j, true));
+        }
+        None
+    }
+}
+
+/// Enumerates Curve pools from meta registries and pool factories
+pub struct CurveDiscovery<P: Provider + Clone> {
+    pub multicall: Multicall<P>,
+    pub registries: Vec<ICurveMetaRegistryInstance<P>>,
+    pub factories: Vec<ICurveFactoryInstance<P>>,
+    provider: P,
+}
+
+impl<P: Provider + Clone> CurveDiscovery<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            multicall: Multicall::new(provider.clone()),
+            registries: Vec::new(),
+            factories: Vec::new(),
+            provider,
+        }
+    }
+
+    /// Registers the registry and factory of every Curve deployment of the chain
+    pub fn from_config(config: &ChainConfig, provider: P) -> Self {
+        let mut discovery = Self::new(provider.clone());
+        discovery.multicall = Multicall::new_with_address(config.multicall, provider);
+
+        for dex in config.dexes_of(ProtocolKind::Curve) {
+            if let Some(registry) = dex.registry {
+                discovery.add_registry(registry);
+            }
+            if let Some(factory) = dex.factory {
+                discovery.add_factory(factory);
+            }
+        }
+
+        discovery
+    }
+
+    pub fn add_registry(&mut self, registry: Address) {
+        self.registries
+            .push(ICurveMetaRegistryInstance::new(
+                registry,
+                self.provider.clone(),
+            ));
+    }
+
+    pub fn add_factory(&mut self, factory: Address) {
+        self.factories
+            .push(ICurveFactoryInstance::new(factory, self.provider.clone()));
+    }
+
+    /// Returns every pool known to the registries and factories. Pools listed in
+    /// both keep the registry description.
+    pub async fn discover(&self) -> Result<Vec<CurvePoolInfo>, DiscoveryError> {
+        let mut seen = HashSet::new();
+        let mut pools = Vec::new();
+
+        for registry in &self.registries {
+            for pool in self.discover_registry(registry).await? {
+                if seen.insert(pool.address) {
+                    pools.push(pool);
+                }
+            }
+        }
+
+        for factory in &self.factories {
+            let addresses = self.list_factory(factory).await?;
+            let addresses: Vec<Address> = addresses
+                .into_iter()
+                .filter(|a| !seen.contains(a))
+                .collect();
+            for pool in self.describe_pools(&addresses).await? {
+                if seen.insert(pool.address) {
+                    pools.push(pool);
+                }
+            }
+        }
+
+        Ok(pools)
+    }
+
+    /// Describes every pool of a meta registry using its introspection calls
+    pub async fn discover_registry(
+        &self,
+        registry: &ICurveMetaRegistryInstance<P>,
+    ) -> Result<Vec<CurvePoolInfo>, DiscoveryError> {
+        let registry_address = *registry.address();
+        let count = registry.pool_count().call().await?;
+
+        let calls = (0..count.saturating_to::<u64>())
+            .map(|i| {
+                multicall::encode(
+                    registry_address,
+                    &ICurveMetaRegistry::pool_listCall {
+                        index: U256::from(i),
+                    },
+                )
+            })
+            .collect();
+        let addresses: Vec<Address> = self
+            .multicall
+            .try_aggregate(calls)
+            .await?
+            .iter()
+            .filter_map(multicall::decode::<ICurveMetaRegistry::pool_listCall>)
+            .filter(|a| *a != Address::ZERO)
+            .collect();
+
+        let mut calls = Vec::with_capacity(addresses.len() * 5);
+        for pool in &addresses {
+            calls.push(multicall::encode(
+                registry_address,
+                &get_n_coinsCall {
+                    pool: *pool,
+                },
+            ));
+            calls.push(multicall::encode(
+                registry_address,
+                &get_coinsCall {
+                    pool: *pool,
+                },
+            ));
+            calls.push(multicall::encode(
+                registry_address,
+                &get_underlying_coinsCall {
+                    pool: *pool,
+                },
+            ));
+            calls.push(multicall::encode(
+                registry_address,
+                &is_metaCall {
+                    pool: *pool,
+                },
+            ));
+            calls.push(multicall::encode(*pool, &gammaCall {}));
+        }
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        let mut pools = Vec::with_capacity(addresses.len());
+        for (pool, result) in addresses.into_iter().zip(results.chunks(5)) {
+            let Some(n_coins) = multicall::decode::<get_n_coinsCall>(&result[0]) else {
+                continue;
+            };
+            let n_coins = n_coins.saturating_to::<usize>().min(MAX_COINS);
+
+            let coins = multicall::decode::<get_coinsCall>(&result[1])
+                .map(|c| non_zero(&c[..n_coins]))
+                .unwrap_or_default();
+            let underlying_coins =
+                multicall::decode::<get_underlying_coinsCall>(&result[2])
+                    .map(|c| non_zero(&c[..]))
+                    .unwrap_or_default();
+            let is_meta = multicall::decode::<is_metaCall>(&result[3]).unwrap_or(false);
+            let is_crypto = multicall::decode::<gammaCall>(&result[4]).is_some();
+
+            pools.push(CurvePoolInfo {
+                address: pool,
+                kind: classify(is_meta, is_crypto),
+                coins,
+                underlying_coins,
+            });
+        }
+
+        Ok(pools)
+    }
+
+    /// Lists every pool deployed by a factory
+    pub async fn list_factory(
+        &self,
+        factory: &ICurveFactoryInstance<P>,
+    ) -> Result<Vec<Address>, DiscoveryError> {
+        let factory_address = *factory.address();
+        let count = factory.pool_count().call().await?;
+
+        let calls = (0..count.saturating_to::<u64>())
+            .map(|i| {
+                multicall::encode(
+                    factory_address,
+                    &ICurveFactory::pool_listCall {
+                        index: U256::from(i),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(self
+            .multicall
+            .try_aggregate(calls)
+            .await?
+            .iter()
+            .filter_map(multicall::decode::<ICurveFactory::pool_listCall>)
+            .filter(|a| *a != Address::ZERO)
+            .collect())
+    }
+
+    /// Describes pools by probing the pool contracts directly. Used for factory pools
+    /// that are not in a registry: coins are read until `coins(i)` reverts, a
+    /// `base_pool` marks a meta pool and a `gamma` marks a crypto pool.
+    pub async fn describe_pools(
+        &self,
+        addresses: &[Address],
+    ) -> Result<Vec<CurvePoolInfo>, DiscoveryError> {
+        const CALLS_PER_POOL: usize = MAX_COINS + 2;
+
+        let mut calls = Vec::with_capacity(addresses.len() * CALLS_PER_POOL);
+        for pool in addresses {
+            for i in 0..MAX_COINS {
+                calls.push(multicall::encode(
+                    *pool,
+                    &coinsCall {
+                        index: U256::from(i),
+                    },
+                ));
+            }
+            calls.push(multicall::encode(*pool, &base_poolCall {}));
+            calls.push(multicall::encode(*pool, &gammaCall {}));
+        }
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        let mut pools = Vec::with_capacity(addresses.len());
+        let mut base_pools = Vec::new();
+        for (pool, result) in addresses
+            .iter()
+            .zip(results.chunks(CALLS_PER_POOL))
+        {
+            let coins = read_coins(&result[..MAX_COINS]);
+            if coins.is_empty() {
+                continue;
+            }
+            let base_pool = multicall::decode::<base_poolCall>(&result[MAX_COINS])
+                .filter(|b| *b != Address::ZERO);
+            let is_crypto =
+                multicall::decode::<gammaCall>(&result[MAX_COINS + 1]).is_some();
+
+            if let Some(base) = base_pool {
+                base_pools.push((pools.len(), base));
+            }
+            pools.push(CurvePoolInfo {
+                address: *pool,
+                kind: classify(base_pool.is_some(), is_crypto),
+                coins,
+                underlying_coins: Vec::new(),
+            });
+        }
+
+        // meta pools trade the first coin plus every coin of their base pool
+        let bases: Vec<Address> = base_pools.iter().map(|(_, b)| *b).collect();
+        let base_coins = self.read_pool_coins(&bases).await?;
+        for ((idx, _), coins) in base_pools.into_iter().zip(base_coins) {
+            let pool = &mut pools[idx];
+            let mut underlying = vec![pool.coins[0]];
+            underlying.extend(coins);
+            pool.underlying_coins = underlying;
+        }
+
+        Ok(pools)
+    }
+
+    async fn read_pool_coins(
+        &self,
+        pools: &[Address],
+    ) -> Result<Vec<Vec<Address>>, DiscoveryError> {
+        let mut calls = Vec::with_capacity(pools.len() * MAX_COINS);
+        for pool in pools {
+            for i in 0..MAX_COINS {
+                calls.push(multicall::encode(
+                    *pool,
+                    &coinsCall {
+                        index: U256::from(i),
+                    },
+                ));
+            }
+        }
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        Ok(results
+            .chunks(MAX_COINS)
+            .map(read_coins)
+            .collect())
+    }
+}
+
+fn classify(is_meta: bool, is_crypto: bool) -> CurvePoolKind {
+    if is_meta {
+        CurvePoolKind::Meta
+    } else if is_crypto {
+        CurvePoolKind::Crypto
+    } else {
+        CurvePoolKind::Plain
+    }
+}
+
+/// Reads `coins(i)` results in order, stopping at the first revert
+fn read_coins(results: &[Option<alloy::primitives::Bytes>]) -> Vec<Address> {
+    results
+        .iter()
+        .map_while(multicall::decode::<coinsCall>)
+        .take_while(|c| *c != Address::ZERO)
+        .collect()
+}
+
+fn non_zero(coins: &[Address]) -> Vec<Address> {
+    coins
+        .iter()
+        .copied()
+        .take_while(|c| *c != Address::ZERO)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{
+        address,
+        Bytes,
+    };
+
+    use super::*;
+    use crate::multicall::tests::{
+        mocked,
+        push_aggregate,
+        ret,
+    };
+
+    const META: Address = address!("00000000000000000000000000000000000000a1");
+    const CRYPTO: Address = address!("00000000000000000000000000000000000000a2");
+    const EMPTY: Address = address!("00000000000000000000000000000000000000a3");
+    const BASE: Address = address!("00000000000000000000000000000000000000b1");
+    const LUSD: Address = address!("0000000000000000000000000000000000000001");
+    const LP: Address = address!("0000000000000000000000000000000000000002");
+    const DAI: Address = address!("0000000000000000000000000000000000000003");
+    const USDC: Address = address!("0000000000000000000000000000000000000004");
+    const USDT: Address = address!("0000000000000000000000000000000000000005");
+
+    /// `coins(i)` results of a pool, reverting past the last coin
+    fn coins(list: &[Address]) -> Vec<Option<Bytes>> {
+        (0..MAX_COINS)
+            .map(|i| list.get(i).and_then(ret::<coinsCall>))
+            .collect()
+    }
+
+    #[test]
+    fn meta_takes_precedence() {
+        assert_eq!(classify(true, true), CurvePoolKind::Meta);
+        assert_eq!(classify(true, false), CurvePoolKind::Meta);
+        assert_eq!(classify(false, true), CurvePoolKind::Crypto);
+        assert_eq!(classify(false, false), CurvePoolKind::Plain);
+    }
+
+    #[test]
+    fn coins_stop_at_revert_or_zero() {
+        assert_eq!(read_coins(&coins(&[DAI, USDC])), vec![DAI, USDC]);
+
+        let mut results = coins(&[
+            DAI, USDC, USDT,
+        ]);
+        results[1] = ret::<coinsCall>(&Address::ZERO);
+        assert_eq!(read_coins(&results), vec![DAI]);
+
+        assert!(read_coins(&coins(&[])).is_empty());
+    }
+
+    #[test]
+    fn indices_fall_back_to_underlying() {
+        let pool = CurvePoolInfo {
+            address: META,
+            kind: CurvePoolKind::Meta,
+            coins: vec![
+                LUSD, LP,
+            ],
+            underlying_coins: vec![
+                LUSD, DAI, USDC, USDT,
+            ],
+        };
+
+        assert_eq!(pool.coin_indices(LUSD, LP), Some((0, 1, false)));
+        assert_eq!(pool.coin_indices(LUSD, USDT), Some((0, 3, true)));
+        assert_eq!(pool.coin_indices(USDC, DAI), Some((2, 1, true)));
+        assert_eq!(pool.coin_indices(LP, DAI), None);
+    }
+
+    #[tokio::test]
+    async fn meta_pools_get_base_coins() {
+        let (asserter, provider) = mocked();
+        let discovery = CurveDiscovery::new(provider);
+
+        let mut results = coins(&[
+            LUSD, LP,
+        ]);
+        results.push(ret::<base_poolCall>(&BASE));
+        results.push(None);
+        results.extend(coins(&[
+            USDT, DAI, USDC,
+        ]));
+        results.push(None);
+        results.push(ret::<gammaCall>(&U256::from(1)));
+        results.extend(coins(&[]));
+        results.push(None);
+        results.push(None);
+        push_aggregate(&asserter, results);
+        push_aggregate(
+            &asserter,
+            coins(&[
+                DAI, USDC, USDT,
+            ]),
+        );
+
+        let pools = discovery
+            .describe_pools(&[
+                META, CRYPTO, EMPTY,
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            pools,
+            vec![
+                CurvePoolInfo {
+                    address: META,
+                    kind: CurvePoolKind::Meta,
+                    coins: vec![LUSD, LP],
+                    underlying_coins: vec![LUSD, DAI, USDC, USDT],
+                },
+                CurvePoolInfo {
+                    address: CRYPTO,
+                    kind: CurvePoolKind::Crypto,
+                    coins: vec![USDT, DAI, USDC],
+                    underlying_coins: Vec::new(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod curve;
+pub mod resolver;
+pub mod v2_discovery;
+pub mod v3_indexer;
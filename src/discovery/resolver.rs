@@ -0,0 +1,355 @@
+use alloy::primitives::{
+    aliases::{I24, U24},
+    Address,
+};
+use alloy_provider::Provider;
+use futures::future::join_all;
+
+use crate::{
+    any_pool::{AnyPool, V4Key},
+    config::{ChainConfig, ProtocolKind},
+    err::DiscoveryError,
+    multicall::{self, Multicall},
+    pool_address::sort_tokens,
+    sol_types::{
+        IUniswapV2Factory::getPairCall,
+        IUniswapV3Factory::{feeAmountTickSpacingCall, getPoolCall},
+        StateView::StateViewInstance,
+    },
+    v2_base::V2Key,
+    v2_pool::V2Pool,
+    v3_pool::V3Pool,
+    v4_pool::V4Pool,
+};
+
+/// Fee tiers probed on every V3 factory, both Uniswap and PancakeSwap ones
+pub const DEFAULT_V3_FEES: [u32; 5] = [
+    100, 500, 2500, 3000, 10000,
+];
+
+/// Finds every live pool between two tokens across the registered factories.
+/// V2 pairs come from `getPair`, V3 pools from `getPool` for each enabled fee tier
+/// and V4 pools from the set of known keys.
+pub struct PairResolver<P: Provider + Clone> {
+    pub provider: P,
+    pub multicall: Multicall<P>,
+    pub v2_factories: Vec<(Address, u32)>,
+    pub v3_factories: Vec<Address>,
+    pub v3_fees: Vec<U24>,
+    pub v4_state_view: Option<StateViewInstance<P>>,
+    pub v4_keys: Vec<V4Key>,
+}
+
+impl<P: Provider + Clone> PairResolver<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            multicall: Multicall::new(provider.clone()),
+            provider,
+            v2_factories: Vec::new(),
+            v3_factories: Vec::new(),
+            v3_fees: DEFAULT_V3_FEES
+                .iter()
+                .map(|f| U24::from(*f))
+                .collect(),
+            v4_state_view: None,
+            v4_keys: Vec::new(),
+        }
+    }
+
+    /// Registers every V2, V3 and V4 deployment of the chain. V3 fee tiers are the
+    /// union of all configured schedules.
+    pub fn from_config(config: &ChainConfig, provider: P) -> Self {
+        let mut resolver = Self::new(provider.clone());
+        resolver.multicall = Multicall::new_with_address(config.multicall, provider);
+
+        for dex in config.dexes_of(ProtocolKind::UniswapV2) {
+            if let Some(factory) = dex.factory {
+                resolver.add_v2_factory(factory, dex.v2_fee());
+            }
+        }
+        for dex in config.dexes_of(ProtocolKind::UniswapV3) {
+            if let Some(factory) = dex.factory {
+                resolver.add_v3_factory(factory);
+            }
+            for fee in &dex.fees {
+                let fee = U24::from(*fee);
+                if !resolver.v3_fees.contains(&fee) {
+                    resolver.v3_fees.push(fee);
+                }
+            }
+        }
+        if let Some(state_view) = config
+            .dexes_of(ProtocolKind::UniswapV4)
+            .find_map(|d| d.state_view)
+        {
+            resolver.set_v4_state_view(state_view);
+        }
+
+        resolver
+    }
+
+    pub fn add_v2_factory(&mut self, factory: Address, fee: u32) {
+        self.v2_factories.push((factory, fee));
+    }
+
+    pub fn add_v3_factory(&mut self, factory: Address) {
+        self.v3_factories.push(factory);
+    }
+
+    pub fn set_v4_state_view(&mut self, state_view: Address) {
+        self.v4_state_view =
+            Some(StateViewInstance::new(state_view, self.provider.clone()));
+    }
+
+    /// Known V4 keys, e.g. from a discovery cache. Only the ones matching the
+    /// requested pair are used.
+    pub fn add_v4_keys(&mut self, keys: impl IntoIterator<Item = V4Key>) {
+        self.v4_keys.extend(keys);
+    }
+
+    /// Returns every pool for the pair that synced with liquidity
+    pub async fn resolve(
+        &self,
+        token_a: Address,
+        token_b: Address,
+    ) -> Result<Vec<AnyPool<P>>, DiscoveryError> {
+        let (token0, token1) = sort_tokens(token_a, token_b);
+
+        let mut pools = self.resolve_v2(token0, token1).await?;
+        pools.append(&mut self.resolve_v3(token0, token1).await?);
+
+        let synced = join_all(pools.iter_mut().map(|p| p.super_sync())).await;
+        let mut live: Vec<AnyPool<P>> = pools
+            .into_iter()
+            .zip(synced)
+            .filter_map(|(pool, result)| result.ok().map(|_| pool))
+            .collect();
+
+        live.append(&mut self.resolve_v4(token0, token1).await);
+
+        Ok(live)
+    }
+
+    async fn resolve_v2(
+        &self,
+        token0: Address,
+        token1: Address,
+    ) -> Result<Vec<AnyPool<P>>, DiscoveryError> {
+        if self.v2_factories.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let calls = self
+            .v2_factories
+            .iter()
+            .map(|(factory, _)| {
+                multicall::encode(
+                    *factory,
+                    &getPairCall {
+                        tokenA: token0,
+                        tokenB: token1,
+                    },
+                )
+            })
+            .collect();
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        let mut pools = Vec::new();
+        for ((factory, fee), result) in self.v2_factories.iter().zip(results) {
+            let Some(pair) = multicall::decode::<getPairCall>(&result) else {
+                continue;
+            };
+            if pair == Address::ZERO {
+                continue;
+            }
+            let key = V2Key {
+                fee: *fee,
+                address: pair,
+                token0,
+                token1,
+            };
+            pools.push(AnyPool::V2(V2Pool::new_from_key(
+                key,
+                *factory,
+                self.provider.clone(),
+            )));
+        }
+
+        Ok(pools)
+    }
+
+    async fn resolve_v3(
+        &self,
+        token0: Address,
+        token1: Address,
+    ) -> Result<Vec<AnyPool<P>>, DiscoveryError> {
+        let mut candidates = Vec::new();
+        let mut calls = Vec::new();
+        for factory in &self.v3_factories {
+            for fee in &self.v3_fees {
+                candidates.push((*factory, *fee));
+                calls.push(multicall::encode(
+                    *factory,
+                    &feeAmountTickSpacingCall {
+                        fee: *fee,
+                    },
+                ));
+                calls.push(multicall::encode(
+                    *factory,
+                    &getPoolCall {
+                        tokenA: token0,
+                        tokenB: token1,
+                        fee: *fee,
+                    },
+                ));
+            }
+        }
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        let mut pools = Vec::new();
+        for ((factory, fee), result) in candidates.into_iter().zip(results.chunks(2)) {
+            let (Some(tick_spacing), Some(pool)) = (
+                multicall::decode::<feeAmountTickSpacingCall>(&result[0]),
+                multicall::decode::<getPoolCall>(&result[1]),
+            ) else {
+                continue;
+            };
+            // a zero spacing means the tier is not enabled on this factory
+            if tick_spacing <= I24::ZERO || pool == Address::ZERO {
+                continue;
+            }
+
+            let key = V4Key {
+                currency0: token0,
+                currency1: token1,
+                fee,
+                tickspacing: tick_spacing,
+                hooks: Address::ZERO,
+            };
+            if let Ok(v3_pool) =
+                V3Pool::new_from_key(pool, self.provider.clone(), factory, key)
+            {
+                pools.push(AnyPool::V3(v3_pool));
+            }
+        }
+
+        Ok(pools)
+    }
+
+    async fn resolve_v4(&self, token0: Address, token1: Address) -> Vec<AnyPool<P>> {
+        let Some(state_view) = &self.v4_state_view else {
+            return Vec::new();
+        };
+
+        let futs = self
+            .v4_keys
+            .iter()
+            .filter(|k| k.currency0 == token0 && k.currency1 == token1)
+            .map(|k| V4Pool::new(*k, state_view.clone()));
+
+        join_all(futs)
+            .await
+            .into_iter()
+            .filter_map(|r| r.ok().map(AnyPool::V4))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+    use crate::multicall::tests::{
+        mocked,
+        push_aggregate,
+        ret,
+    };
+
+    const TOKEN0: Address = address!("0000000000000000000000000000000000000001");
+    const TOKEN1: Address = address!("0000000000000000000000000000000000000002");
+    const FACTORY_A: Address = address!("00000000000000000000000000000000000000fa");
+    const FACTORY_B: Address = address!("00000000000000000000000000000000000000fb");
+    const FACTORY_C: Address = address!("00000000000000000000000000000000000000fc");
+    const POOL: Address = address!("00000000000000000000000000000000000000a1");
+
+    #[tokio::test]
+    async fn v2_skips_missing_pairs() {
+        let (asserter, provider) = mocked();
+        let mut resolver = PairResolver::new(provider);
+        resolver.add_v2_factory(FACTORY_A, 30);
+        resolver.add_v2_factory(FACTORY_B, 25);
+        resolver.add_v2_factory(FACTORY_C, 30);
+
+        // A has no pair, B has one and the call to C reverts
+        push_aggregate(
+            &asserter,
+            vec![
+                ret::<getPairCall>(&Address::ZERO),
+                ret::<getPairCall>(&POOL),
+                None,
+            ],
+        );
+
+        let pools = resolver.resolve_v2(TOKEN0, TOKEN1).await.unwrap();
+        assert_eq!(pools.len(), 1);
+        let AnyPool::V2(pool) = &pools[0] else {
+            panic!("expected a V2 pool");
+        };
+        assert_eq!(pool.key.address, POOL);
+        assert_eq!(pool.key.fee, 25);
+        assert_eq!(pool.factory, FACTORY_B);
+    }
+
+    #[tokio::test]
+    async fn v3_skips_disabled_tiers() {
+        let (asserter, provider) = mocked();
+        let mut resolver = PairResolver::new(provider);
+        resolver.add_v3_factory(FACTORY_A);
+        resolver.v3_fees = vec![
+            U24::from(100),
+            U24::from(500),
+            U24::from(3000),
+        ];
+
+        // 100 is not enabled, 500 has a pool and 3000 is enabled without a pool
+        push_aggregate(
+            &asserter,
+            vec![
+                ret::<feeAmountTickSpacingCall>(&I24::ZERO),
+                ret::<getPoolCall>(&POOL),
+                ret::<feeAmountTickSpacingCall>(&I24::try_from(10).unwrap()),
+                ret::<getPoolCall>(&POOL),
+                ret::<feeAmountTickSpacingCall>(&I24::try_from(60).unwrap()),
+                ret::<getPoolCall>(&Address::ZERO),
+            ],
+        );
+
+        let pools = resolver.resolve_v3(TOKEN0, TOKEN1).await.unwrap();
+        assert_eq!(pools.len(), 1);
+        let AnyPool::V3(pool) = &pools[0] else {
+            panic!("expected a V3 pool");
+        };
+        assert_eq!(*pool.contract.address(), POOL);
+        assert_eq!(pool.factory, FACTORY_A);
+        assert_eq!(pool.key.fee, U24::from(500));
+        assert_eq!(pool.key.tickspacing, I24::try_from(10).unwrap());
+        assert_eq!((pool.key.currency0, pool.key.currency1), (TOKEN0, TOKEN1));
+    }
+
+    #[tokio::test]
+    async fn no_factories_makes_no_calls() {
+        let (_asserter, provider) = mocked();
+        let resolver = PairResolver::new(provider);
+
+        assert!(resolver
+            .resolve(TOKEN1, TOKEN0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
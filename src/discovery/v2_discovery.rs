@@ -0,0 +1,255 @@
+use alloy::primitives::{Address, U256};
+use alloy_provider::Provider;
+
+use crate::{
+    config::{ChainConfig, DexConfig},
+    err::DiscoveryError,
+    multicall::{self, Multicall},
+    sol_types::{
+        IUniswapV2Factory::{allPairsCall, IUniswapV2FactoryInstance},
+        IUniswapV2Pair::{getReservesCall, token0Call, token1Call},
+    },
+    v2_base::{V2Key, V2State},
+    v2_pool::V2Pool,
+};
+
+const DEFAULT_BATCH_SIZE: u64 = 1000;
+
+/// Pages through a V2 factory `allPairs` list. Every call to `next_batch` fetches one
+/// page of pair addresses and their tokens/reserves, so a scan over millions of pairs
+/// can be stopped and resumed from `next_index`.
+pub struct V2Discovery<P: Provider + Clone> {
+    pub factory: IUniswapV2FactoryInstance<P>,
+    pub multicall: Multicall<P>,
+    pub fee: u32,
+    pub batch_size: u64,
+    next_index: u64,
+    total: Option<u64>,
+}
+
+impl<P: Provider + Clone> V2Discovery<P> {
+    pub fn new(factory: Address, fee: u32, provider: P) -> Self {
+        Self {
+            factory: IUniswapV2FactoryInstance::new(factory, provider.clone()),
+            multicall: Multicall::new(provider),
+            fee,
+            batch_size: DEFAULT_BATCH_SIZE,
+            next_index: 0,
+            total: None,
+        }
+    }
+
+    /// Uses the dex factory and fee with the chain's Multicall3. Returns `None` when
+    /// the dex has no factory configured.
+    pub fn from_config(
+        config: &ChainConfig,
+        dex: &DexConfig,
+        provider: P,
+    ) -> Option<Self> {
+        let mut discovery = Self::new(dex.factory?, dex.v2_fee(), provider.clone());
+        discovery.multicall = Multicall::new_with_address(config.multicall, provider);
+        Some(discovery)
+    }
+
+    /// Resume from an index previously saved from `next_index`
+    pub fn resume_from(mut self, index: u64) -> Self {
+        self.next_index = index;
+        self
+    }
+
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Refreshes the pair count from the factory
+    pub async fn total(&mut self) -> Result<u64, DiscoveryError> {
+        let length = self.factory.allPairsLength().call().await?;
+        let length = length.saturating_to::<u64>();
+        self.total = Some(length);
+        Ok(length)
+    }
+
+    /// True once every pair known at the last `total` refresh was returned
+    pub fn is_done(&self) -> bool {
+        match self.total {
+            Some(total) => self.next_index >= total,
+            None => false,
+        }
+    }
+
+    /// Fetches the next page of pairs. The index only advances when the page was
+    /// fetched, so a failed multicall can simply be retried. Single `allPairs` calls
+    /// that revert inside the page are skipped.
+    pub async fn next_batch(&mut self) -> Result<Vec<V2Pool<P>>, DiscoveryError> {
+        let total = match self.total {
+            Some(total) if self.next_index < total => total,
+            _ => self.total().await?,
+        };
+
+        let start = self.next_index;
+        let end = (start + self.batch_size).min(total);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let factory = *self.factory.address();
+        let calls = (start..end)
+            .map(|i| multicall::encode(factory, &allPairsCall(U256::from(i))))
+            .collect();
+
+        let pairs: Vec<Address> = self
+            .multicall
+            .try_aggregate(calls)
+            .await?
+            .iter()
+            .filter_map(multicall::decode::<allPairsCall>)
+            .collect();
+
+        let pools = self.fetch_pairs(&pairs).await?;
+        self.next_index = end;
+
+        Ok(pools)
+    }
+
+    /// Builds pools for known pair addresses with tokens and reserves filled in.
+    /// Pairs that revert on any of the calls are skipped.
+    pub async fn fetch_pairs(
+        &self,
+        pairs: &[Address],
+    ) -> Result<Vec<V2Pool<P>>, DiscoveryError> {
+        let mut calls = Vec::with_capacity(pairs.len() * 3);
+        for pair in pairs {
+            calls.push(multicall::encode(*pair, &token0Call {}));
+            calls.push(multicall::encode(*pair, &token1Call {}));
+            calls.push(multicall::encode(*pair, &getReservesCall {}));
+        }
+
+        let results = self.multicall.try_aggregate(calls).await?;
+        let provider = self.factory.provider().clone();
+        let factory = *self.factory.address();
+
+        let mut pools = Vec::with_capacity(pairs.len());
+        for (pair, result) in pairs.iter().zip(results.chunks(3)) {
+            let (Some(token0), Some(token1), Some(reserves)) = (
+                multicall::decode::<token0Call>(&result[0]),
+                multicall::decode::<token1Call>(&result[1]),
+                multicall::decode::<getReservesCall>(&result[2]),
+            ) else {
+                continue;
+            };
+
+            let key = V2Key {
+                fee: self.fee,
+                address: *pair,
+                token0,
+                token1,
+            };
+            let mut pool = V2Pool::new_from_key(key, factory, provider.clone());
+            pool.state = V2State {
+                reserves0: U256::from(reserves.reserve0),
+                reserves1: U256::from(reserves.reserve1),
+            };
+            pools.push(pool);
+        }
+
+        Ok(pools)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, aliases::U112};
+
+    use super::*;
+    use crate::{
+        multicall::tests::{mocked, push_aggregate, push_call, ret},
+        sol_types::{
+            IUniswapV2Factory::allPairsLengthCall,
+            IUniswapV2Pair::getReservesReturn,
+        },
+    };
+
+    const FACTORY: Address = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+    const PAIR_A: Address = address!("00000000000000000000000000000000000000a1");
+    const PAIR_B: Address = address!("00000000000000000000000000000000000000b1");
+    const TOKEN0: Address = address!("0000000000000000000000000000000000000001");
+    const TOKEN1: Address = address!("0000000000000000000000000000000000000002");
+
+    fn reserves(reserve0: u64, reserve1: u64) -> Option<alloy::primitives::Bytes> {
+        ret::<getReservesCall>(&getReservesReturn {
+            reserve0: U112::from(reserve0),
+            reserve1: U112::from(reserve1),
+            blockTimestampLast: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn pages_from_resume_index() {
+        let (asserter, provider) = mocked();
+        let mut discovery = V2Discovery::new(FACTORY, 30, provider).resume_from(1);
+        discovery.batch_size = 2;
+
+        // first page covers pairs 1 and 2, the second `allPairs` call reverts
+        push_call::<allPairsLengthCall>(&asserter, &U256::from(4));
+        push_aggregate(
+            &asserter,
+            vec![
+                ret::<allPairsCall>(&PAIR_A),
+                None,
+            ],
+        );
+        push_aggregate(
+            &asserter,
+            vec![
+                ret::<token0Call>(&TOKEN0),
+                ret::<token1Call>(&TOKEN1),
+                reserves(1_000, 2_000),
+            ],
+        );
+
+        let pools = discovery.next_batch().await.unwrap();
+        assert_eq!(pools.len(), 1);
+        let key = &pools[0].key;
+        assert_eq!(
+            (key.address, key.token0, key.token1, key.fee),
+            (PAIR_A, TOKEN0, TOKEN1, 30)
+        );
+        assert_eq!(pools[0].state.reserves0, U256::from(1_000));
+        assert_eq!(pools[0].state.reserves1, U256::from(2_000));
+        assert_eq!(discovery.next_index(), 3);
+        assert!(!discovery.is_done());
+
+        // the cached total is reused, a pair whose `token0` reverts is skipped
+        push_aggregate(&asserter, vec![ret::<allPairsCall>(&PAIR_B)]);
+        push_aggregate(
+            &asserter,
+            vec![
+                None,
+                ret::<token1Call>(&TOKEN1),
+                reserves(1, 1),
+            ],
+        );
+
+        assert!(discovery.next_batch().await.unwrap().is_empty());
+        assert_eq!(discovery.next_index(), 4);
+        assert!(discovery.is_done());
+
+        // once done the count is refreshed and no new pair means an empty page
+        push_call::<allPairsLengthCall>(&asserter, &U256::from(4));
+        assert!(discovery.next_batch().await.unwrap().is_empty());
+        assert_eq!(discovery.next_index(), 4);
+    }
+
+    #[tokio::test]
+    async fn failed_page_keeps_index() {
+        let (asserter, provider) = mocked();
+        let mut discovery = V2Discovery::new(FACTORY, 30, provider);
+
+        push_call::<allPairsLengthCall>(&asserter, &U256::from(2));
+        asserter.push_failure_msg("rate limited");
+
+        assert!(discovery.next_batch().await.is_err());
+        assert_eq!(discovery.next_index(), 0);
+        assert!(!discovery.is_done());
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use alloy::{
+    primitives::Address,
+    rpc::types::{Filter, Log},
+};
+use alloy_provider::Provider;
+use alloy_sol_types::SolEvent;
+
+use crate::{
+    any_pool::V4Key,
+    checkpoint::Checkpoint,
+    config::DexConfig,
+    err::DiscoveryError,
+    log_scanner::LogScanner,
+    sol_types::IUniswapV3Factory::PoolCreated,
+    v3_pool::V3Pool,
+};
+
+/// Walks a V3 factory's `PoolCreated` logs from its deployment block forward. The
+/// event already carries the whole pool key, so pools are built without any calls to
+/// the pool contracts.
+pub struct V3Indexer<P: Provider + Clone> {
+    pub factory: Address,
+    pub scanner: LogScanner<P>,
+}
+
+impl<P: Provider + Clone> V3Indexer<P> {
+    pub fn new(factory: Address, deployment_block: u64, provider: P) -> Self {
+        let filter = Filter::new()
+            .address(factory)
+            .event_signature(PoolCreated::SIGNATURE_HASH);
+
+        Self {
+            factory,
+            scanner: LogScanner::new(provider, filter, deployment_block),
+        }
+    }
+
+    /// Starts at the dex deployment block. Returns `None` when the dex has no
+    /// factory configured.
+    pub fn from_config(dex: &DexConfig, provider: P) -> Option<Self> {
+        Some(Self::new(dex.factory?, dex.deployment_block, provider))
+    }
+
+    /// Persists progress to `path` after every scanned window. A checkpoint already
+    /// stored there takes precedence over the deployment block.
+    pub fn with_checkpoint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.scanner = self.scanner.with_checkpoint_file(path);
+        self
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.scanner.checkpoint
+    }
+
+    /// Scans one window starting at the checkpoint and advances it. Returns an empty
+    /// list once `to_block` was reached.
+    pub async fn next_batch(
+        &mut self,
+        to_block: u64,
+    ) -> Result<Vec<V3Pool<P>>, DiscoveryError> {
+        let Some(window) = self.scanner.next_window(to_block).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut pools = Vec::with_capacity(window.logs.len());
+        for log in window.logs {
+            let Some((address, key)) = decode_pool_created(&log) else {
+                continue;
+            };
+            if let Ok(pool) = V3Pool::new_from_key(
+                address,
+                self.scanner.provider.clone(),
+                self.factory,
+                key,
+            ) {
+                pools.push(pool);
+            }
+        }
+
+        Ok(pools)
+    }
+
+    /// Scans every window up to `to_block`
+    pub async fn run(&mut self, to_block: u64) -> Result<Vec<V3Pool<P>>, DiscoveryError> {
+        let mut pools = Vec::new();
+        while !self.scanner.is_done(to_block) {
+            let mut batch = self.next_batch(to_block).await?;
+            pools.append(&mut batch);
+        }
+        Ok(pools)
+    }
+}
+
+/// Extracts the pool address and key from a `PoolCreated` log
+pub fn decode_pool_created(log: &Log) -> Option<(Address, V4Key)> {
+    let event = log.log_decode::<PoolCreated>().ok()?.inner.data;
+
+    let key = V4Key {
+        currency0: event.token0,
+        currency1: event.token1,
+        fee: event.fee,
+        tickspacing: event.tickSpacing,
+        hooks: Address::ZERO,
+    };
+
+    Some((event.pool, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{
+        address,
+        aliases::{I24, U24},
+    };
+
+    use super::*;
+
+    const FACTORY: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984");
+    const POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    fn pool_created() -> Log {
+        let event = PoolCreated {
+            token0: USDC,
+            token1: WETH,
+            fee: U24::from(500),
+            tickSpacing: I24::try_from(10).unwrap(),
+            pool: POOL,
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: FACTORY,
+                data: event.encode_log_data(),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_pool_key() {
+        let (pool, key) = decode_pool_created(&pool_created()).unwrap();
+
+        assert_eq!(pool, POOL);
+        assert_eq!(key.currency0, USDC);
+        assert_eq!(key.currency1, WETH);
+        assert_eq!(key.fee, U24::from(500));
+        assert_eq!(key.tickspacing, I24::try_from(10).unwrap());
+        assert_eq!(key.hooks, Address::ZERO);
+    }
+
+    #[test]
+    fn skips_other_events() {
+        let mut log = pool_created();
+        log.inner.data.topics_mut()[0] = alloy::primitives::B256::ZERO;
+
+        assert!(decode_pool_created(&log).is_none());
+    }
+}
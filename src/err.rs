@@ -21,6 +21,18 @@ pub enum TradeError {
     Fetch(alloy_contract::Error),
     Math(MathError),
     V2,
+    /// The receipt was quoted on another pool kind
+    Receipt,
+    /// The pool state is older than the caller accepts
+    Stale {
+        synced_block: Option<u64>,
+        head: u64,
+    },
+    /// The tick set of a concentrated pool is older than the caller accepts
+    StaleTicks {
+        synced_block: Option<u64>,
+        head: u64,
+    },
 }
 
 impl From<TickError> for TradeError {
@@ -39,3 +51,183 @@ impl From<alloy_contract::Error> for TradeError {
         TradeError::Fetch(value)
     }
 }
+
+#[derive(Debug)]
+pub enum DiscoveryError {
+    Fetch(alloy_contract::Error),
+    Rpc(alloy::transports::TransportError),
+    Io(std::io::Error),
+    Decode,
+}
+
+impl From<alloy_contract::Error> for DiscoveryError {
+    fn from(value: alloy_contract::Error) -> Self {
+        DiscoveryError::Fetch(value)
+    }
+}
+
+impl From<alloy::transports::TransportError> for DiscoveryError {
+    fn from(value: alloy::transports::TransportError) -> Self {
+        DiscoveryError::Rpc(value)
+    }
+}
+
+impl From<std::io::Error> for DiscoveryError {
+    fn from(value: std::io::Error) -> Self {
+        DiscoveryError::Io(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    UnknownFormat,
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(value: std::io::Error) -> Self {
+        ConfigError::Io(value)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(value: toml::de::Error) -> Self {
+        ConfigError::Toml(value)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(value: serde_json::Error) -> Self {
+        ConfigError::Json(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum SimulationError {
+    Rpc(alloy::transports::TransportError),
+    Fetch(alloy_contract::Error),
+    /// The snapshot lacks an account or slot the pool layout needs
+    MissingState,
+    /// The executor has no result for the requested swap
+    Unsupported,
+    /// The EVM halted or the swap reverted with the pool's own error
+    Execution(String),
+    Trade(Box<TradeError>),
+}
+
+impl From<alloy::transports::TransportError> for SimulationError {
+    fn from(value: alloy::transports::TransportError) -> Self {
+        SimulationError::Rpc(value)
+    }
+}
+
+impl From<alloy_contract::Error> for SimulationError {
+    fn from(value: alloy_contract::Error) -> Self {
+        SimulationError::Fetch(value)
+    }
+}
+
+impl From<TradeError> for SimulationError {
+    fn from(value: TradeError) -> Self {
+        SimulationError::Trade(Box::new(value))
+    }
+}
+
+#[derive(Debug)]
+pub enum JournalError {
+    Rpc(alloy::transports::TransportError),
+    /// The chain forked below the oldest journaled block, pools need a full resync
+    ReorgTooDeep,
+    /// The node returned no block for a requested height
+    MissingBlock(u64),
+}
+
+impl From<alloy::transports::TransportError> for JournalError {
+    fn from(value: alloy::transports::TransportError) -> Self {
+        JournalError::Rpc(value)
+    }
+}
+
+/// Why the registry could not sync a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncError {
+    /// No registry pool under the key
+    UnknownPool,
+    /// The node call failed or its response did not decode
+    Failed,
+}
+
+/// Why a receipt or checkpoint can't be applied to a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// Taken on another pool or pool kind
+    WrongPool,
+    /// The pool state differs from the one the receipt was quoted on
+    Changed,
+}
+
+#[derive(Debug)]
+pub enum CommitError {
+    Trade(Box<TradeError>),
+    State(StateError),
+}
+
+impl From<TradeError> for CommitError {
+    fn from(value: TradeError) -> Self {
+        CommitError::Trade(Box::new(value))
+    }
+}
+
+impl From<StateError> for CommitError {
+    fn from(value: StateError) -> Self {
+        CommitError::State(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum IntentError {
+    /// A hop has no matching pool in the registry
+    UnknownPool,
+    /// The swap would revert on its minimum output or maximum input
+    Slippage,
+    Trade(Box<TradeError>),
+    State(StateError),
+}
+
+impl From<TradeError> for IntentError {
+    fn from(value: TradeError) -> Self {
+        IntentError::Trade(Box::new(value))
+    }
+}
+
+impl From<CommitError> for IntentError {
+    fn from(value: CommitError) -> Self {
+        match value {
+            CommitError::Trade(err) => IntentError::Trade(err),
+            CommitError::State(err) => IntentError::State(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RouteError {
+    /// Hops don't connect or a pool does not hold the hop token
+    Path,
+    Trade(Box<TradeError>),
+}
+
+impl From<TradeError> for RouteError {
+    fn from(value: TradeError) -> Self {
+        RouteError::Trade(Box::new(value))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapError {
+    /// An amount does not fit the width of the router parameter
+    AmountOverflow,
+    /// The receipt was quoted on another pool
+    Receipt,
+}
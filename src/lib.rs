@@ -10,10 +10,23 @@ use tower::ServiceBuilder;
 
 pub mod any_pool;
 pub mod any_trade;
+pub mod checkpoint;
+pub mod config;
+pub mod discovery;
 pub mod err;
-
+pub mod log_scanner;
+pub mod mempool;
+pub mod multicall;
 pub mod pool;
+pub mod pool_address;
+pub mod price;
+pub mod registry;
+pub mod resync;
+pub mod routing;
+pub mod simulation;
 pub mod sol_types;
+pub mod swap;
+pub mod tokens;
 pub mod v2_base;
 pub mod v2_pool;
 pub mod v3_base;
@@ -30,18 +43,10 @@ mod tests {
     //cake - BSC-USD v3
     const V3_CAKE_USD_ADDR: &str = "0xFe4fe5B4575c036aC6D5cCcFe13660020270e27A";
 
-    const V4_ADDR: &str = "0xd13Dd3D6E93f276FAfc9Db9E6BB47C1180aeE0c4";
-
     const V2_BABYDODGE_USD: &str = "0xc736ca3d9b1e90af4230bd8f9626528b3d4e0ee0";
 
     const V2_CAKE_WBNB: &str = "0x0ed7e52944161450477ee417de9cd3a859b14fd0";
 
-    const BNB_PROVIDER: &str = "https://binance.llamarpc.com";
-    const BNB_PROVIDER_2: &str = "https://bsc.rpc.blxrbdn.com";
-    const BNB_PROVIDER_3: &str = "https://bsc-mainnet.public.blastapi.io";
-    const BNB_PROVIDER_4: &str = "https://bsc.drpc.org";
-    use std::fs::write;
-
     use alloy::{
         primitives::{Address, U256},
         rpc::types::{Bundle, Filter},
@@ -51,6 +56,8 @@ mod tests {
 
     use crate::{
         any_pool::AnyPool,
+        config::ChainConfig,
+        log_scanner::LogScanner,
         pool::UniPool,
         sol_types::{
             PoolKey,
@@ -73,62 +80,35 @@ mod tests {
             hooks: Address::ZERO,
         };
 
-        let bnb_provider_urls = vec![
-            BNB_PROVIDER.to_string(),
-            BNB_PROVIDER_2.to_string(),
-            BNB_PROVIDER_3.to_string(),
-            BNB_PROVIDER_4.to_string(),
-        ];
+        let config = ChainConfig::bsc();
+        let v4_addr = config
+            .dex("uniswap_v4")
+            .and_then(|d| d.state_view)
+            .unwrap();
 
-        let provider = generate_fallback_provider(bnb_provider_urls);
+        let provider = config.provider();
         let current_block = provider.get_block_number().await.unwrap();
-        let step = 500;
-        let mut from_block = current_block;
-        let mut to_block = current_block - step;
         let min_block = 50000000;
-        println!("block {}", current_block);
         let mut keys = Vec::new();
 
         keys.push(v4_key);
         let mut keis = Vec::new();
 
-        while to_block > min_block {
-            let v4_created_event_filter = Filter::new()
-                .address(Address::from_str(V4_ADDR).unwrap())
-                .from_block(to_block)
-                .to_block(from_block);
-
-            let logs = provider.get_logs(&v4_created_event_filter).await;
-
-            match logs {
-                Ok(mut ok) => {
-                    println!("{} - {}", from_block, to_block);
-                    println!("all v4 pools {:?}", ok);
-                    keis.append(&mut ok);
-                    let mut data = String::new();
-                    for k in ok {
-                        data += &k.data().data.to_string();
-                    }
-                    if !data.is_empty() {
-                        if let Err(er) = write(
-                            format!("./v4_events/{}-{}.logs", from_block, to_block),
-                            data,
-                        ) {
-                            println!("err saving {}", er);
-                        }
-                    }
-                }
-                Err(err) => println!("err getting logs {:?}", err),
-            }
+        let v4_created_event_filter = Filter::new().address(v4_addr);
+        let mut scanner =
+            LogScanner::new(provider.clone(), v4_created_event_filter, min_block);
 
-            from_block -= step;
-            to_block -= step;
+        let mut next_block = min_block;
+        while let Some(mut window) = scanner.next_window(current_block).await.unwrap() {
+            assert_eq!(window.from_block, next_block);
+            next_block = window.to_block + 1;
+            keis.append(&mut window.logs);
         }
 
         let usdc_usd_address: Address = V3_USDC_USD.parse().unwrap();
         let usdt_bnb_address: Address = V3_USDT_BNB_ADDR.parse().unwrap();
         let cake_usd_address: Address = V3_CAKE_USD_ADDR.parse().unwrap();
-        let v4_state_view: Address = V4_ADDR.parse().unwrap();
+        let v4_state_view: Address = v4_addr;
 
         let v2_babydodge_usd: Address = V2_BABYDODGE_USD.parse().unwrap();
         let v2_cake_wbnb: Address = V2_CAKE_WBNB.parse().unwrap();
@@ -166,7 +146,7 @@ mod tests {
         */
 
         let v4_state_view =
-            StateViewInstance::new(V4_ADDR.parse().unwrap(), provider.clone());
+            StateViewInstance::new(v4_addr, provider.clone());
 
         for key in keys.clone() {
             if let Ok(v4_pool) = V4Pool::new(key.into(), v4_state_view.clone()).await {
@@ -0,0 +1,205 @@
+use std::{path::PathBuf, time::Duration};
+
+use alloy::{
+    rpc::types::{Filter, Log},
+    transports::TransportError,
+};
+use alloy_provider::Provider;
+
+use crate::{checkpoint::Checkpoint, err::DiscoveryError};
+
+const DEFAULT_STEP: u64 = 2000;
+const DEFAULT_MAX_STEP: u64 = 100_000;
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const DEFAULT_TARGET_LOGS: usize = 2000;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Error messages nodes use when a `getLogs` window is too wide or returns too much
+const RANGE_ERROR_HINTS: [&str; 8] = [
+    "too many",
+    "range",
+    "limit",
+    "exceed",
+    "more than",
+    "too large",
+    "response size",
+    "timeout",
+];
+
+/// One scanned block window, both ends inclusive
+#[derive(Debug, Clone)]
+pub struct ScanWindow {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub logs: Vec<Log>,
+}
+
+/// Walks `getLogs` forward over a block range with an adaptive window.
+/// The window is halved whenever the node rejects it for size, doubled again while
+/// responses stay small, and any other failure is retried on the same window. The
+/// checkpoint only moves past windows that were actually returned, so nothing is
+/// skipped when a scan stops on an error.
+pub struct LogScanner<P: Provider> {
+    pub provider: P,
+    /// Address and topic template, the block range is set per window
+    pub filter: Filter,
+    pub step: u64,
+    pub min_step: u64,
+    pub max_step: u64,
+    pub max_retries: u32,
+    /// Responses below half of this grow the window
+    pub target_logs: usize,
+    pub checkpoint: Checkpoint,
+    pub checkpoint_path: Option<PathBuf>,
+}
+
+impl<P: Provider> LogScanner<P> {
+    pub fn new(provider: P, filter: Filter, from_block: u64) -> Self {
+        Self {
+            provider,
+            filter,
+            step: DEFAULT_STEP,
+            min_step: 1,
+            max_step: DEFAULT_MAX_STEP,
+            max_retries: DEFAULT_MAX_RETRIES,
+            target_logs: DEFAULT_TARGET_LOGS,
+            checkpoint: Checkpoint::new(from_block),
+            checkpoint_path: None,
+        }
+    }
+
+    /// Persists progress to `path` after every window. A checkpoint already stored
+    /// there takes precedence over `from_block`.
+    pub fn with_checkpoint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if let Some(saved) = Checkpoint::load(&path) {
+            self.checkpoint = saved;
+        }
+        self.checkpoint_path = Some(path);
+        self
+    }
+
+    pub fn is_done(&self, to_block: u64) -> bool {
+        self.checkpoint.next_block > to_block
+    }
+
+    /// Fetches the next window up to `to_block` and advances the checkpoint.
+    /// Returns `None` once `to_block` was passed.
+    pub async fn next_window(
+        &mut self,
+        to_block: u64,
+    ) -> Result<Option<ScanWindow>, DiscoveryError> {
+        let from_block = self.checkpoint.next_block;
+        if from_block > to_block {
+            return Ok(None);
+        }
+
+        let mut retries = 0;
+        loop {
+            let window_end = from_block
+                .saturating_add(self.step.max(1) - 1)
+                .min(to_block);
+            let filter = self
+                .filter
+                .clone()
+                .from_block(from_block)
+                .to_block(window_end);
+
+            match self.provider.get_logs(&filter).await {
+                Ok(logs) => {
+                    if logs.len() < self.target_logs / 2 {
+                        self.step = (self.step * 2).min(self.max_step);
+                    }
+
+                    self.checkpoint.next_block = window_end + 1;
+                    if let Some(path) = &self.checkpoint_path {
+                        self.checkpoint.save(path)?;
+                    }
+
+                    return Ok(Some(ScanWindow {
+                        from_block,
+                        to_block: window_end,
+                        logs,
+                    }));
+                }
+                Err(err) if is_range_error(&err) && self.step > self.min_step => {
+                    self.step = (self.step / 2).max(self.min_step);
+                }
+                Err(err) => {
+                    retries += 1;
+                    if retries > self.max_retries {
+                        return Err(err.into());
+                    }
+                    tokio::time::sleep(RETRY_DELAY * retries).await;
+                }
+            }
+        }
+    }
+
+    /// Scans every window up to `to_block` and returns all logs
+    pub async fn scan(&mut self, to_block: u64) -> Result<Vec<Log>, DiscoveryError> {
+        let mut logs = Vec::new();
+        while let Some(mut window) = self.next_window(to_block).await? {
+            logs.append(&mut window.logs);
+        }
+        Ok(logs)
+    }
+}
+
+/// True when the node rejected the request because of the block range or the
+/// response size, as opposed to a transport failure
+pub fn is_range_error(err: &TransportError) -> bool {
+    let Some(payload) = err.as_error_resp() else {
+        return false;
+    };
+    // -32005 is the "limit exceeded" code used by most providers
+    if payload.code == -32005 {
+        return true;
+    }
+
+    let message = payload.message.to_lowercase();
+    RANGE_ERROR_HINTS
+        .iter()
+        .any(|hint| message.contains(hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_error(code: i64, message: &str) -> TransportError {
+        let payload = serde_json::json!({
+            "code": code,
+            "message": message,
+        });
+        TransportError::ErrorResp(serde_json::from_value(payload).unwrap())
+    }
+
+    #[test]
+    fn provider_range_errors() {
+        let messages = [
+            // Alchemy
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range",
+            // Infura
+            "query returned more than 10000 results",
+            // QuickNode
+            "eth_getLogs is limited to a 10,000 range",
+            // geth / erigon
+            "block range too large",
+            // BSC public nodes
+            "exceed maximum block range: 5000",
+            // Ankr
+            "too many blocks requested",
+            // llamarpc
+            "request timeout",
+        ];
+        for message in messages {
+            assert!(is_range_error(&rpc_error(-32000, message)), "{}", message);
+        }
+        assert!(is_range_error(&rpc_error(-32005, "anything")));
+
+        assert!(!is_range_error(&rpc_error(-32000, "execution reverted")));
+        assert!(!is_range_error(&rpc_error(-32601, "method not found")));
+        assert!(!is_range_error(&TransportError::NullResp));
+    }
+}
@@ -0,0 +1,1201 @@
+use std::collections::HashMap;
+
+use alloy::{
+    consensus::{
+        Transaction,
+        TxEnvelope,
+    },
+    eips::eip2718::Decodable2718,
+    primitives::{
+        aliases::{
+            U160,
+            U24,
+        },
+        Address,
+        Bytes,
+        U256,
+    },
+};
+use alloy_provider::Provider;
+use alloy_sol_types::{
+    SolCall,
+    SolValue,
+};
+
+use crate::{
+    any_pool::V4Key,
+    config::{
+        ChainConfig,
+        ProtocolKind,
+    },
+    err::IntentError,
+    pool::{
+        PoolCheckpoint,
+        UniPool,
+    },
+    pool_address::v4_pool_id,
+    registry::{
+        PoolRef,
+        PoolRegistry,
+    },
+    sol_types::{
+        ISwapRouter,
+        ISwapRouter02,
+        IUniswapV2Router02::{
+            swapETHForExactTokensCall,
+            swapExactETHForTokensCall,
+            swapExactETHForTokensSupportingFeeOnTransferTokensCall,
+            swapExactTokensForETHCall,
+            swapExactTokensForETHSupportingFeeOnTransferTokensCall,
+            swapExactTokensForTokensCall,
+            swapExactTokensForTokensSupportingFeeOnTransferTokensCall,
+            swapTokensForExactETHCall,
+            swapTokensForExactTokensCall,
+        },
+        IUniversalRouter::{
+            execute_0Call,
+            execute_1Call,
+        },
+        IV4Router::{
+            ExactInputSingleParams,
+            ExactOutputSingleParams,
+        },
+    },
+    swap::{
+        v3::V3Route,
+        v4::{
+            COMMAND_MASK,
+            CONTRACT_BALANCE,
+            PERMIT2_TRANSFER_FROM,
+            SWAP_EXACT_IN_SINGLE,
+            SWAP_EXACT_OUT_SINGLE,
+            TRANSFER,
+            V2_SWAP_EXACT_IN,
+            V2_SWAP_EXACT_OUT,
+            V3_SWAP_EXACT_IN,
+            V3_SWAP_EXACT_OUT,
+            V4_SWAP,
+        },
+    },
+    v3_base::states::TradeReceipt,
+};
+
+/// One pool step of a swap intent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hop {
+    V2 {
+        token_in: Address,
+        token_out: Address,
+    },
+    V3 {
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+    },
+    V4 {
+        key: V4Key,
+        zero_for_one: bool,
+    },
+}
+
+impl Hop {
+    pub fn token_in(&self) -> Address {
+        match self {
+            Hop::V2 {
+                token_in,
+                ..
+            }
+            | Hop::V3 {
+                token_in,
+                ..
+            } => *token_in,
+            Hop::V4 {
+                key,
+                zero_for_one,
+            } => {
+                if *zero_for_one {
+                    key.currency0
+                } else {
+                    key.currency1
+                }
+            }
+        }
+    }
+
+    pub fn token_out(&self) -> Address {
+        match self {
+            Hop::V2 {
+                token_out,
+                ..
+            }
+            | Hop::V3 {
+                token_out,
+                ..
+            } => *token_out,
+            Hop::V4 {
+                key,
+                zero_for_one,
+            } => {
+                if *zero_for_one {
+                    key.currency1
+                } else {
+                    key.currency0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmount {
+    ExactIn {
+        amount_in: U256,
+        min_out: U256,
+    },
+    ExactOut {
+        amount_out: U256,
+        max_in: U256,
+    },
+}
+
+/// A swap a pending transaction will run, hops in execution order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapIntent {
+    pub router: Address,
+    /// Factory the router resolves V2/V3 hops with, any pool matches when `None`
+    pub factory: Option<Address>,
+    pub hops: Vec<Hop>,
+    pub amount: SwapAmount,
+}
+
+/// Decodes transactions sent to known routers into swap intents
+#[derive(Debug, Clone, Default)]
+pub struct MempoolDecoder {
+    /// Router address and the factory of its V2/V3 hops
+    pub routers: HashMap<Address, Option<Address>>,
+}
+
+impl MempoolDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every configured router, V2 and V3 routers bound to their dex factory
+    pub fn from_config(config: &ChainConfig) -> Self {
+        let mut decoder = Self::new();
+        for dex in &config.dexes {
+            let Some(router) = dex.router else {
+                continue;
+            };
+            let factory = match dex.kind {
+                ProtocolKind::UniswapV2 | ProtocolKind::UniswapV3 => dex.factory,
+                _ => None,
+            };
+            decoder.add_router(router, factory);
+        }
+        decoder
+    }
+
+    pub fn add_router(&mut self, router: Address, factory: Option<Address>) {
+        self.routers.insert(router, factory);
+    }
+
+    /// Decodes a raw EIP-2718 transaction as broadcast by `eth_sendRawTransaction`
+    pub fn decode_raw(&self, mut raw: &[u8]) -> Vec<SwapIntent> {
+        let Ok(tx) = TxEnvelope::decode_2718(&mut raw) else {
+            return Vec::new();
+        };
+        let Some(to) = tx.to() else {
+            return Vec::new();
+        };
+        self.decode_call(to, tx.input(), tx.value())
+    }
+
+    /// Swaps in a call to `to`, empty when `to` is not a known router or the call is
+    /// not a supported swap
+    pub fn decode_call(&self, to: Address, input: &[u8], value: U256) -> Vec<SwapIntent> {
+        let Some(factory) = self.routers.get(&to) else {
+            return Vec::new();
+        };
+        let intent = |hops: Vec<Hop>, amount: SwapAmount| SwapIntent {
+            router: to,
+            factory: *factory,
+            hops,
+            amount,
+        };
+
+        let mut intents = Vec::new();
+        for (hops, amount) in decode_swaps(input, value) {
+            if !hops.is_empty() {
+                intents.push(intent(hops, amount));
+            }
+        }
+        intents
+    }
+}
+
+/// Hops and amounts of every swap in a router call
+fn decode_swaps(input: &[u8], value: U256) -> Vec<(Vec<Hop>, SwapAmount)> {
+    let Some(selector) = input.get(..4) else {
+        return Vec::new();
+    };
+    let exact_in = |amount_in, min_out| SwapAmount::ExactIn {
+        amount_in,
+        min_out,
+    };
+    let exact_out = |amount_out, max_in| SwapAmount::ExactOut {
+        amount_out,
+        max_in,
+    };
+
+    let swap = match selector {
+        s if s == swapExactTokensForTokensCall::SELECTOR => {
+            swapExactTokensForTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(c.amountIn, c.amountOutMin)))
+        }
+        s if s == swapExactTokensForTokensSupportingFeeOnTransferTokensCall::SELECTOR => {
+            swapExactTokensForTokensSupportingFeeOnTransferTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(c.amountIn, c.amountOutMin)))
+        }
+        s if s == swapTokensForExactTokensCall::SELECTOR => {
+            swapTokensForExactTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_out(c.amountOut, c.amountInMax)))
+        }
+        s if s == swapTokensForExactETHCall::SELECTOR => {
+            swapTokensForExactETHCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_out(c.amountOut, c.amountInMax)))
+        }
+        s if s == swapETHForExactTokensCall::SELECTOR => {
+            swapETHForExactTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_out(c.amountOut, value)))
+        }
+        s if s == swapExactETHForTokensCall::SELECTOR => {
+            swapExactETHForTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(value, c.amountOutMin)))
+        }
+        s if s == swapExactETHForTokensSupportingFeeOnTransferTokensCall::SELECTOR => {
+            swapExactETHForTokensSupportingFeeOnTransferTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(value, c.amountOutMin)))
+        }
+        s if s == swapExactTokensForETHCall::SELECTOR => {
+            swapExactTokensForETHCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(c.amountIn, c.amountOutMin)))
+        }
+        s if s == swapExactTokensForETHSupportingFeeOnTransferTokensCall::SELECTOR => {
+            swapExactTokensForETHSupportingFeeOnTransferTokensCall::abi_decode(input)
+                .ok()
+                .map(|c| (v2_hops(&c.path), exact_in(c.amountIn, c.amountOutMin)))
+        }
+        s if s == ISwapRouter::exactInputSingleCall::SELECTOR => {
+            ISwapRouter::exactInputSingleCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    let hop = Hop::V3 {
+                        token_in: p.tokenIn,
+                        token_out: p.tokenOut,
+                        fee: p.fee,
+                    };
+                    (vec![hop], exact_in(p.amountIn, p.amountOutMinimum))
+                })
+        }
+        s if s == ISwapRouter::exactInputCall::SELECTOR => {
+            ISwapRouter::exactInputCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    (
+                        v3_hops(&p.path, false),
+                        exact_in(p.amountIn, p.amountOutMinimum),
+                    )
+                })
+        }
+        s if s == ISwapRouter::exactOutputSingleCall::SELECTOR => {
+            ISwapRouter::exactOutputSingleCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    let hop = Hop::V3 {
+                        token_in: p.tokenIn,
+                        token_out: p.tokenOut,
+                        fee: p.fee,
+                    };
+                    (vec![hop], exact_out(p.amountOut, p.amountInMaximum))
+                })
+        }
+        s if s == ISwapRouter::exactOutputCall::SELECTOR => {
+            ISwapRouter::exactOutputCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    (
+                        v3_hops(&p.path, true),
+                        exact_out(p.amountOut, p.amountInMaximum),
+                    )
+                })
+        }
+        s if s == ISwapRouter02::exactInputSingleCall::SELECTOR => {
+            ISwapRouter02::exactInputSingleCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    let hop = Hop::V3 {
+                        token_in: p.tokenIn,
+                        token_out: p.tokenOut,
+                        fee: p.fee,
+                    };
+                    (vec![hop], exact_in(p.amountIn, p.amountOutMinimum))
+                })
+        }
+        s if s == ISwapRouter02::exactInputCall::SELECTOR => {
+            ISwapRouter02::exactInputCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    (
+                        v3_hops(&p.path, false),
+                        exact_in(p.amountIn, p.amountOutMinimum),
+                    )
+                })
+        }
+        s if s == ISwapRouter02::exactOutputSingleCall::SELECTOR => {
+            ISwapRouter02::exactOutputSingleCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    let hop = Hop::V3 {
+                        token_in: p.tokenIn,
+                        token_out: p.tokenOut,
+                        fee: p.fee,
+                    };
+                    (vec![hop], exact_out(p.amountOut, p.amountInMaximum))
+                })
+        }
+        s if s == ISwapRouter02::exactOutputCall::SELECTOR => {
+            ISwapRouter02::exactOutputCall::abi_decode(input)
+                .ok()
+                .map(|c| {
+                    let p = c.params;
+                    (
+                        v3_hops(&p.path, true),
+                        exact_out(p.amountOut, p.amountInMaximum),
+                    )
+                })
+        }
+        s if s == ISwapRouter::multicallCall::SELECTOR => {
+            let Ok(call) = ISwapRouter::multicallCall::abi_decode(input) else {
+                return Vec::new();
+            };
+            return decode_multicall(&call.data, value);
+        }
+        s if s == ISwapRouter02::multicall_0Call::SELECTOR => {
+            let Ok(call) = ISwapRouter02::multicall_0Call::abi_decode(input) else {
+                return Vec::new();
+            };
+            return decode_multicall(&call.data, value);
+        }
+        s if s == ISwapRouter02::multicall_1Call::SELECTOR => {
+            let Ok(call) = ISwapRouter02::multicall_1Call::abi_decode(input) else {
+                return Vec::new();
+            };
+            return decode_multicall(&call.data, value);
+        }
+        s if s == execute_0Call::SELECTOR => {
+            let Ok(call) = execute_0Call::abi_decode(input) else {
+                return Vec::new();
+            };
+            return decode_commands(&call.commands, &call.inputs);
+        }
+        s if s == execute_1Call::SELECTOR => {
+            let Ok(call) = execute_1Call::abi_decode(input) else {
+                return Vec::new();
+            };
+            return decode_commands(&call.commands, &call.inputs);
+        }
+        _ => None,
+    };
+
+    swap.into_iter().collect()
+}
+
+/// Swaps of a router multicall. SwapRouter02 reads an exact input of zero as its
+/// own balance, the output of the swap before it.
+fn decode_multicall(data: &[Bytes], value: U256) -> Vec<(Vec<Hop>, SwapAmount)> {
+    let mut swaps = Vec::new();
+    for (hops, amount) in data
+        .iter()
+        .flat_map(|inner| decode_swaps(inner, value))
+    {
+        match amount {
+            SwapAmount::ExactIn {
+                amount_in,
+                min_out,
+            } if amount_in.is_zero() => chain(&mut swaps, hops, min_out),
+            _ => swaps.push((hops, amount)),
+        }
+    }
+    swaps
+}
+
+/// Universal Router commands, the ones that don't swap are skipped. A
+/// `CONTRACT_BALANCE` or already paid (zero) input is the amount the last transfer
+/// paid, or else the output of the swap before it.
+fn decode_commands(commands: &[u8], inputs: &[Bytes]) -> Vec<(Vec<Hop>, SwapAmount)> {
+    let mut swaps = Vec::new();
+    let mut paid = None;
+    for (command, input) in commands.iter().zip(inputs) {
+        match command & COMMAND_MASK {
+            PERMIT2_TRANSFER_FROM => {
+                // (token, recipient, amount)
+                paid = <(Address, Address, U160)>::abi_decode_params(input)
+                    .ok()
+                    .map(|(_, _, amount)| U256::from(amount));
+            }
+            TRANSFER => {
+                paid = <(Address, Address, U256)>::abi_decode_params(input)
+                    .ok()
+                    .map(|(_, _, amount)| amount)
+                    .filter(|amount| *amount != CONTRACT_BALANCE);
+            }
+            V3_SWAP_EXACT_IN => {
+                // (recipient, amountIn, amountOutMin, path, payerIsUser)
+                if let Ok((_, amount_in, min_out, path, _)) =
+                    <(Address, U256, U256, Bytes, bool)>::abi_decode_params(input)
+                {
+                    let amount_in = (amount_in != CONTRACT_BALANCE).then_some(amount_in);
+                    let hops = v3_hops(&path, false);
+                    push_exact_in(&mut swaps, &mut paid, hops, amount_in, min_out);
+                }
+            }
+            V3_SWAP_EXACT_OUT => {
+                paid = None;
+                if let Ok((_, amount_out, max_in, path, _)) =
+                    <(Address, U256, U256, Bytes, bool)>::abi_decode_params(input)
+                {
+                    swaps.push((
+                        v3_hops(&path, true),
+                        SwapAmount::ExactOut {
+                            amount_out,
+                            max_in,
+                        },
+                    ));
+                }
+            }
+            V2_SWAP_EXACT_IN => {
+                if let Ok((_, amount_in, min_out, path, _)) =
+                    <(Address, U256, U256, Vec<Address>, bool)>::abi_decode_params(input)
+                {
+                    let amount_in = (amount_in != CONTRACT_BALANCE
+                        && !amount_in.is_zero())
+                    .then_some(amount_in);
+                    let hops = v2_hops(&path);
+                    push_exact_in(&mut swaps, &mut paid, hops, amount_in, min_out);
+                }
+            }
+            V2_SWAP_EXACT_OUT => {
+                paid = None;
+                if let Ok((_, amount_out, max_in, path, _)) =
+                    <(Address, U256, U256, Vec<Address>, bool)>::abi_decode_params(input)
+                {
+                    swaps.push((
+                        v2_hops(&path),
+                        SwapAmount::ExactOut {
+                            amount_out,
+                            max_in,
+                        },
+                    ));
+                }
+            }
+            V4_SWAP => decode_v4_actions(input, &mut swaps),
+            _ => (),
+        }
+    }
+    swaps
+}
+
+/// Pushes an exact input swap, one without a literal amount spends what was paid
+/// right before it
+fn push_exact_in(
+    swaps: &mut Vec<(Vec<Hop>, SwapAmount)>,
+    paid: &mut Option<U256>,
+    hops: Vec<Hop>,
+    amount_in: Option<U256>,
+    min_out: U256,
+) {
+    let paid = paid.take();
+    match amount_in.or(paid) {
+        Some(amount_in) => swaps.push((
+            hops,
+            SwapAmount::ExactIn {
+                amount_in,
+                min_out,
+            },
+        )),
+        None => chain(swaps, hops, min_out),
+    }
+}
+
+/// Continues the last swap with `hops` spending its whole output. Skipped when there
+/// is no swap before or the tokens don't connect.
+fn chain(swaps: &mut Vec<(Vec<Hop>, SwapAmount)>, hops: Vec<Hop>, min_out: U256) {
+    let Some((last_hops, last_amount)) = swaps.last_mut() else {
+        return;
+    };
+    let connects = last_hops
+        .last()
+        .zip(hops.first())
+        .is_some_and(|(last, first)| last.token_out() == first.token_in());
+    if !connects {
+        return;
+    }
+
+    match last_amount {
+        SwapAmount::ExactIn {
+            min_out: last_min,
+            ..
+        } => {
+            last_hops.extend(hops);
+            *last_min = min_out;
+        }
+        SwapAmount::ExactOut {
+            amount_out,
+            ..
+        } => {
+            let amount_in = *amount_out;
+            swaps.push((
+                hops,
+                SwapAmount::ExactIn {
+                    amount_in,
+                    min_out,
+                },
+            ));
+        }
+    }
+}
+
+/// Single pool swaps of a `V4_SWAP` action list. An exact input of zero is the open
+/// delta, the output of the swap before it.
+fn decode_v4_actions(input: &[u8], swaps: &mut Vec<(Vec<Hop>, SwapAmount)>) {
+    let Ok((actions, params)) = <(Bytes, Vec<Bytes>)>::abi_decode_params(input) else {
+        return;
+    };
+
+    for (action, param) in actions.iter().zip(&params) {
+        match *action {
+            SWAP_EXACT_IN_SINGLE => {
+                if let Ok(p) = ExactInputSingleParams::abi_decode(param) {
+                    let hop = Hop::V4 {
+                        key: p.poolKey.into(),
+                        zero_for_one: p.zeroForOne,
+                    };
+                    let amount_in = U256::from(p.amountIn);
+                    let min_out = U256::from(p.amountOutMinimum);
+                    if amount_in.is_zero() {
+                        chain(swaps, vec![hop], min_out);
+                    } else {
+                        swaps.push((
+                            vec![hop],
+                            SwapAmount::ExactIn {
+                                amount_in,
+                                min_out,
+                            },
+                        ));
+                    }
+                }
+            }
+            SWAP_EXACT_OUT_SINGLE => {
+                if let Ok(p) = ExactOutputSingleParams::abi_decode(param) {
+                    let hop = Hop::V4 {
+                        key: p.poolKey.into(),
+                        zero_for_one: p.zeroForOne,
+                    };
+                    swaps.push((
+                        vec![hop],
+                        SwapAmount::ExactOut {
+                            amount_out: U256::from(p.amountOut),
+                            max_in: U256::from(p.amountInMaximum),
+                        },
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn v2_hops(path: &[Address]) -> Vec<Hop> {
+    path.windows(2)
+        .map(|w| Hop::V2 {
+            token_in: w[0],
+            token_out: w[1],
+        })
+        .collect()
+}
+
+/// Hops of a packed path, exact output paths run from the output token back
+fn v3_hops(path: &[u8], reversed: bool) -> Vec<Hop> {
+    let Some(mut route) = V3Route::decode(path) else {
+        return Vec::new();
+    };
+    if reversed {
+        route = route.reversed();
+    }
+    route
+        .tokens
+        .windows(2)
+        .zip(&route.fees)
+        .map(|(w, fee)| Hop::V3 {
+            token_in: w[0],
+            token_out: w[1],
+            fee: *fee,
+        })
+        .collect()
+}
+
+/// What an intent did to the registry pools
+#[derive(Debug, Clone)]
+pub struct IntentOutcome {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// One receipt per hop
+    pub receipts: Vec<TradeReceipt>,
+}
+
+/// Runs `intents` in order on the registry pools and hands the resulting
+/// post-pending registry to `inspect` for prices and quotes. Every pool an intent
+/// touched is restored afterwards, so `inspect` should only quote. Intents that
+/// would revert leave the pools untouched.
+pub fn with_pending<P: Provider, R>(
+    registry: &mut PoolRegistry<P>,
+    intents: &[SwapIntent],
+    inspect: impl FnOnce(&mut PoolRegistry<P>) -> R,
+) -> (Vec<Result<IntentOutcome, IntentError>>, R) {
+    let mut touched = HashMap::new();
+    let outcomes = intents
+        .iter()
+        .map(|intent| apply_intent(registry, intent, &mut touched))
+        .collect();
+
+    let result = inspect(registry);
+
+    for (pool, checkpoint) in touched {
+        if let Some(pool) = registry.get_mut(&pool) {
+            let _ = pool.rollback(checkpoint);
+        }
+    }
+    (outcomes, result)
+}
+
+/// Commits one intent, recording the first checkpoint of every pool in `touched`
+pub fn apply_intent<P: Provider>(
+    registry: &mut PoolRegistry<P>,
+    intent: &SwapIntent,
+    touched: &mut HashMap<PoolRef, PoolCheckpoint>,
+) -> Result<IntentOutcome, IntentError> {
+    let pools = intent
+        .hops
+        .iter()
+        .map(|hop| resolve(registry, intent.factory, hop))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(IntentError::UnknownPool)?;
+
+    let amount_in = match intent.amount {
+        SwapAmount::ExactIn {
+            amount_in,
+            ..
+        } => amount_in,
+        SwapAmount::ExactOut {
+            amount_out,
+            max_in,
+        } => required_input(registry, &pools, amount_out, max_in)?,
+    };
+
+    let mut local = HashMap::new();
+    let result = run_route(registry, &pools, amount_in, &mut local);
+    let accepted = match (&result, intent.amount) {
+        (
+            Ok(outcome),
+            SwapAmount::ExactIn {
+                min_out,
+                ..
+            },
+        ) => outcome.amount_out >= min_out,
+        (
+            Ok(outcome),
+            SwapAmount::ExactOut {
+                amount_out,
+                ..
+            },
+        ) => outcome.amount_out >= amount_out,
+        (Err(_), _) => false,
+    };
+
+    if !accepted {
+        restore(registry, local);
+        return result.and(Err(IntentError::Slippage));
+    }
+    for (pool, checkpoint) in local {
+        touched.entry(pool).or_insert(checkpoint);
+    }
+    result
+}
+
+/// Registry pool and swap direction of a hop
+fn resolve<P: Provider>(
+    registry: &PoolRegistry<P>,
+    factory: Option<Address>,
+    hop: &Hop,
+) -> Option<(PoolRef, bool)> {
+    let (pool, token_in) = match hop {
+        Hop::V2 {
+            token_in,
+            token_out,
+        } => (
+            registry.find(*token_in, *token_out, None, factory)?,
+            *token_in,
+        ),
+        Hop::V3 {
+            token_in,
+            token_out,
+            fee,
+        } => (
+            registry.find(*token_in, *token_out, Some(*fee), factory)?,
+            *token_in,
+        ),
+        Hop::V4 {
+            key,
+            zero_for_one,
+        } => {
+            let pool = PoolRef::V4(v4_pool_id(key));
+            registry.get(&pool)?;
+            return Some((pool, *zero_for_one));
+        }
+    };
+    let from0 = *registry.get(&pool)?.get_a() == token_in;
+    Some((pool, from0))
+}
+
+/// Trades through `pools` committing every hop, the first checkpoint of each pool
+/// goes to `local`
+fn run_route<P: Provider>(
+    registry: &mut PoolRegistry<P>,
+    pools: &[(PoolRef, bool)],
+    amount_in: U256,
+    local: &mut HashMap<PoolRef, PoolCheckpoint>,
+) -> Result<IntentOutcome, IntentError> {
+    let mut amount = amount_in;
+    let mut receipts = Vec::with_capacity(pools.len());
+
+    for (key, from0) in pools {
+        let pool = registry
+            .get_mut(key)
+            .ok_or(IntentError::UnknownPool)?;
+        local
+            .entry(*key)
+            .or_insert_with(|| pool.checkpoint());
+        let receipt = pool.trade_and_commit(amount, *from0)?;
+        amount = receipt.amount_out;
+        receipts.push(receipt);
+    }
+
+    Ok(IntentOutcome {
+        amount_in,
+        amount_out: amount,
+        receipts,
+    })
+}
+
+fn restore<P: Provider>(
+    registry: &mut PoolRegistry<P>,
+    checkpoints: HashMap<PoolRef, PoolCheckpoint>,
+) {
+    for (pool, checkpoint) in checkpoints {
+        if let Some(pool) = registry.get_mut(&pool) {
+            let _ = pool.rollback(checkpoint);
+        }
+    }
+}
+
+/// Output of the route for `amount_in`, leaving the pools as they were
+fn quote_route<P: Provider>(
+    registry: &mut PoolRegistry<P>,
+    pools: &[(PoolRef, bool)],
+    amount_in: U256,
+) -> Option<U256> {
+    let mut local = HashMap::new();
+    let out = run_route(registry, pools, amount_in, &mut local).ok();
+    restore(registry, local);
+    out.map(|o| o.amount_out)
+}
+
+/// Smallest input up to `max_in` that buys `amount_out`, by bisection on the route
+/// quote
+fn required_input<P: Provider>(
+    registry: &mut PoolRegistry<P>,
+    pools: &[(PoolRef, bool)],
+    amount_out: U256,
+    max_in: U256,
+) -> Result<U256, IntentError> {
+    let enough = |registry: &mut PoolRegistry<P>, amount_in| {
+        quote_route(registry, pools, amount_in).is_some_and(|out| out >= amount_out)
+    };
+    if !enough(registry, max_in) {
+        return Err(IntentError::Slippage);
+    }
+
+    let (mut low, mut high) = (U256::ZERO, max_in);
+    while high - low > U256::ONE {
+        let mid = low + (high - low) / U256::from(2);
+        if enough(registry, mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(high)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        consensus::{
+            SignableTransaction,
+            TxEip1559,
+        },
+        eips::eip2718::Encodable2718,
+        primitives::{
+            aliases::I24,
+            Signature,
+            TxKind,
+        },
+    };
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{
+        any_pool::AnyPool,
+        routing::tests::E18,
+        sol_types::ISwapRouter02::{
+            ExactInputParams,
+            ExactOutputParams,
+        },
+        swap::{
+            v2::swap_exact_tokens_for_tokens,
+            v4::{
+                UniversalRouter,
+                NATIVE,
+            },
+            SwapSettings,
+        },
+        v3_base::ticks::Tick,
+        v3_pool::V3Pool,
+    };
+
+    fn v3<P: Provider>(
+        provider: P,
+        address: u8,
+        token0: Address,
+        token1: Address,
+    ) -> AnyPool<P> {
+        let key = V4Key {
+            currency0: token0,
+            currency1: token1,
+            fee: U24::from(3000),
+            tickspacing: I24::try_from(10).unwrap(),
+            ..Default::default()
+        };
+        let mut pool = V3Pool::new_from_key(
+            Address::repeat_byte(address),
+            provider,
+            Address::ZERO,
+            key,
+        )
+        .unwrap();
+        pool.state.x96price = U256::ONE << 96;
+        pool.state.liquidity = U256::from(1_000 * E18);
+        pool.state.ticks.insert_ticks(vec![
+            Tick {
+                tick: I24::try_from(-600).unwrap(),
+                liquidity_net: Some(1_000 * E18 as i128),
+            },
+            Tick {
+                tick: I24::try_from(600).unwrap(),
+                liquidity_net: Some(-(1_000 * E18 as i128)),
+            },
+        ]);
+        AnyPool::V3(pool)
+    }
+
+    #[test]
+    fn decodes_router_calls() {
+        let router = Address::repeat_byte(0xaa);
+        let universal = Address::repeat_byte(0xbb);
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let settings = SwapSettings::new(50, 1);
+
+        let mut decoder = MempoolDecoder::new();
+        decoder.add_router(router, None);
+        decoder.add_router(universal, None);
+
+        let request = swap_exact_tokens_for_tokens(
+            router,
+            vec![
+                a, b, c,
+            ],
+            U256::from(1000),
+            U256::from(2000),
+            Address::ZERO,
+            &settings,
+            false,
+        );
+        let tx = TxEip1559 {
+            to: TxKind::Call(router),
+            input: request.input.input().cloned().unwrap_or_default(),
+            ..Default::default()
+        };
+        let raw =
+            TxEnvelope::from(tx.into_signed(Signature::test_signature())).encoded_2718();
+
+        let intents = decoder.decode_raw(&raw);
+        assert_eq!(intents.len(), 1);
+        assert_eq!(
+            intents[0].hops,
+            vec![
+                Hop::V2 {
+                    token_in: a,
+                    token_out: b,
+                },
+                Hop::V2 {
+                    token_in: b,
+                    token_out: c,
+                },
+            ]
+        );
+        assert_eq!(
+            intents[0].amount,
+            SwapAmount::ExactIn {
+                amount_in: U256::from(1000),
+                min_out: settings.min_amount_out(U256::from(2000)),
+            }
+        );
+
+        let key = V4Key {
+            currency0: NATIVE,
+            currency1: a,
+            ..Default::default()
+        };
+        let request = UniversalRouter::new(universal)
+            .exact_input_single(
+                &key,
+                true,
+                U256::from(5),
+                U256::from(7),
+                Bytes::new(),
+                &settings,
+            )
+            .unwrap();
+        let input = request.input.input().cloned().unwrap_or_default();
+        let intents = decoder.decode_call(universal, &input, U256::from(5));
+        assert_eq!(
+            intents[0].hops,
+            vec![
+                Hop::V4 {
+                    key,
+                    zero_for_one: true,
+                }
+            ]
+        );
+
+        // the second swap spends the router balance the first one left
+        let fee = U24::from(500);
+        let path = |from: Address, to: Address| {
+            V3Route {
+                tokens: vec![
+                    from, to,
+                ],
+                fees: vec![fee],
+            }
+            .encode()
+        };
+        let call = execute_1Call {
+            commands: Bytes::from(vec![
+                V3_SWAP_EXACT_IN,
+                V3_SWAP_EXACT_IN,
+            ]),
+            inputs: vec![
+                (Address::ZERO, U256::from(10), U256::ZERO, path(a, b), true)
+                    .abi_encode_params()
+                    .into(),
+                (
+                    Address::ZERO,
+                    CONTRACT_BALANCE,
+                    U256::from(3),
+                    path(b, c),
+                    false,
+                )
+                    .abi_encode_params()
+                    .into(),
+            ],
+        };
+        let intents = decoder.decode_call(universal, &call.abi_encode(), U256::ZERO);
+        assert_eq!(intents.len(), 1);
+        assert_eq!(
+            intents[0].hops,
+            vec![
+                Hop::V3 {
+                    token_in: a,
+                    token_out: b,
+                    fee,
+                },
+                Hop::V3 {
+                    token_in: b,
+                    token_out: c,
+                    fee,
+                },
+            ]
+        );
+        assert_eq!(
+            intents[0].amount,
+            SwapAmount::ExactIn {
+                amount_in: U256::from(10),
+                min_out: U256::from(3),
+            }
+        );
+
+        // the V2 swap spends what Permit2 paid the pair
+        let call = execute_1Call {
+            commands: Bytes::from(vec![
+                PERMIT2_TRANSFER_FROM,
+                V2_SWAP_EXACT_IN,
+            ]),
+            inputs: vec![
+                (a, Address::repeat_byte(0x11), U160::from(E18))
+                    .abi_encode_params()
+                    .into(),
+                (
+                    Address::ZERO,
+                    U256::ZERO,
+                    U256::from(4),
+                    vec![
+                        a, b,
+                    ],
+                    false,
+                )
+                    .abi_encode_params()
+                    .into(),
+            ],
+        };
+        let intents = decoder.decode_call(universal, &call.abi_encode(), U256::ZERO);
+        assert_eq!(
+            intents[0].amount,
+            SwapAmount::ExactIn {
+                amount_in: U256::from(E18),
+                min_out: U256::from(4),
+            }
+        );
+    }
+
+    #[test]
+    fn pending_v3_path() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let mut registry = PoolRegistry::new(provider.clone());
+        let ab = registry.insert(v3(provider.clone(), 0x10, a, b));
+        registry.insert(v3(provider, 0x11, b, c));
+
+        let router = Address::repeat_byte(0xaa);
+        let mut decoder = MempoolDecoder::new();
+        decoder.add_router(router, None);
+
+        let route = V3Route {
+            tokens: vec![
+                a, b, c,
+            ],
+            fees: vec![U24::from(3000); 2],
+        };
+        let exact_in = ISwapRouter02::exactInputCall {
+            params: ExactInputParams {
+                path: route.encode(),
+                recipient: Address::ZERO,
+                amountIn: U256::from(E18),
+                amountOutMinimum: U256::ZERO,
+            },
+        };
+        // exact output paths start at the output token
+        let exact_out = ISwapRouter02::exactOutputCall {
+            params: ExactOutputParams {
+                path: route.encode_reversed(),
+                recipient: Address::ZERO,
+                amountOut: U256::from(E18 / 2),
+                amountInMaximum: U256::from(2 * E18),
+            },
+        };
+        let call = ISwapRouter02::multicall_0Call {
+            deadline: U256::MAX,
+            data: vec![
+                exact_in.abi_encode().into(),
+                exact_out.abi_encode().into(),
+            ],
+        };
+        let intents = decoder.decode_call(router, &call.abi_encode(), U256::ZERO);
+        assert_eq!(intents.len(), 2);
+        let hops = vec![
+            Hop::V3 {
+                token_in: a,
+                token_out: b,
+                fee: U24::from(3000),
+            },
+            Hop::V3 {
+                token_in: b,
+                token_out: c,
+                fee: U24::from(3000),
+            },
+        ];
+        assert_eq!(intents[0].hops, hops);
+        assert_eq!(intents[1].hops, hops);
+
+        let before = registry.get(&ab).unwrap().checkpoint();
+        let (outcomes, pending) = with_pending(&mut registry, &intents, |registry| {
+            registry.get(&ab).unwrap().checkpoint()
+        });
+        let bought = outcomes[1].as_ref().unwrap();
+        assert!(outcomes[0].is_ok());
+        assert!(bought.amount_out >= U256::from(E18 / 2));
+        assert!(bought.amount_in <= U256::from(2 * E18));
+        assert_ne!(pending, before);
+        assert_eq!(registry.get(&ab).unwrap().checkpoint(), before);
+
+        // an intent that would revert is not committed
+        let greedy = SwapIntent {
+            amount: SwapAmount::ExactIn {
+                amount_in: U256::from(E18),
+                min_out: U256::from(E18),
+            },
+            ..intents[0].clone()
+        };
+        let mut touched = HashMap::new();
+        assert!(matches!(
+            apply_intent(&mut registry, &greedy, &mut touched),
+            Err(IntentError::Slippage)
+        ));
+        assert!(touched.is_empty());
+        assert_eq!(registry.get(&ab).unwrap().checkpoint(), before);
+    }
+}
@@ -0,0 +1,191 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{address, Address, Bytes},
+};
+use alloy_provider::Provider;
+use alloy_sol_types::SolCall;
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::sol_types::IMulticall3::{Call3, IMulticall3Instance};
+
+/// Multicall3 is deployed at the same address on most EVM chains
+pub const MULTICALL3_ADDRESS: Address =
+    address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+const DEFAULT_BATCH_SIZE: usize = 500;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+pub struct Multicall<P: Provider> {
+    pub contract: IMulticall3Instance<P>,
+    pub batch_size: usize,
+    /// Batches in flight at once
+    pub concurrency: usize,
+}
+
+impl<P: Provider> Multicall<P> {
+    pub fn new(provider: P) -> Self {
+        Self::new_with_address(MULTICALL3_ADDRESS, provider)
+    }
+
+    pub fn new_with_address(address: Address, provider: P) -> Self {
+        Self {
+            contract: IMulticall3Instance::new(address, provider),
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Runs every `(target, calldata)` pair through `aggregate3`, splitting into
+    /// `batch_size` chunks with up to `concurrency` of them in flight. Reverted calls
+    /// come back as `None` instead of failing the whole batch.
+    pub async fn try_aggregate(
+        &self,
+        calls: Vec<(Address, Bytes)>,
+    ) -> Result<Vec<Option<Bytes>>, alloy_contract::Error> {
+        self.aggregate_inner(calls, true, None).await
+    }
+
+    /// Same as `try_aggregate` but any reverted call fails the whole request.
+    pub async fn aggregate(
+        &self,
+        calls: Vec<(Address, Bytes)>,
+    ) -> Result<Vec<Bytes>, alloy_contract::Error> {
+        self.aggregate_at(calls, BlockId::latest()).await
+    }
+
+    /// `aggregate` with every batch run at `block`, so results split over several
+    /// requests still describe one state
+    pub async fn aggregate_at(
+        &self,
+        calls: Vec<(Address, Bytes)>,
+        block: BlockId,
+    ) -> Result<Vec<Bytes>, alloy_contract::Error> {
+        let results = self
+            .aggregate_inner(calls, false, Some(block))
+            .await?;
+        Ok(results
+            .into_iter()
+            .map(|r| r.unwrap_or_default())
+            .collect())
+    }
+
+    async fn aggregate_inner(
+        &self,
+        calls: Vec<(Address, Bytes)>,
+        allow_failure: bool,
+        block: Option<BlockId>,
+    ) -> Result<Vec<Option<Bytes>>, alloy_contract::Error> {
+        let batches: Vec<Vec<Call3>> = calls
+            .chunks(self.batch_size.max(1))
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|(target, data)| Call3 {
+                        target: *target,
+                        allowFailure: allow_failure,
+                        callData: data.clone(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // `buffered` keeps the batches in call order
+        let responses: Vec<_> = stream::iter(batches)
+            .map(|batch| async move {
+                let call = self.contract.aggregate3(batch);
+                match block {
+                    Some(block) => call.block(block).call().await,
+                    None => call.call().await,
+                }
+            })
+            .buffered(self.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(responses
+            .into_iter()
+            .flatten()
+            .map(|r| r.success.then_some(r.returnData))
+            .collect())
+    }
+}
+
+/// Builds a multicall entry from a typed sol call
+pub fn encode<C: SolCall>(target: Address, call: &C) -> (Address, Bytes) {
+    (target, Bytes::from(call.abi_encode()))
+}
+
+/// Decodes a multicall result, treating reverts and malformed returns as missing
+pub fn decode<C: SolCall>(result: &Option<Bytes>) -> Option<C::Return> {
+    let bytes = result.as_ref()?;
+    C::abi_decode_returns(bytes).ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::transports::mock::Asserter;
+    use alloy_provider::{ProviderBuilder, RootProvider};
+
+    use super::*;
+    use crate::sol_types::IMulticall3::{aggregate3Call, Call3Result};
+
+    /// Provider answering requests from the queued mock responses
+    pub(crate) fn mocked() -> (Asserter, RootProvider) {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::default().connect_mocked_client(asserter.clone());
+        (asserter, provider)
+    }
+
+    /// Queues an `eth_call` answer returning `value`
+    pub(crate) fn push_call<C: SolCall>(asserter: &Asserter, value: &C::Return) {
+        asserter.push_success(&Bytes::from(C::abi_encode_returns(value)));
+    }
+
+    /// Queues an `aggregate3` answer, `None` entries come back as reverted calls
+    pub(crate) fn push_aggregate(asserter: &Asserter, results: Vec<Option<Bytes>>) {
+        let results = results
+            .into_iter()
+            .map(|r| Call3Result {
+                success: r.is_some(),
+                returnData: r.unwrap_or_default(),
+            })
+            .collect();
+        push_call::<aggregate3Call>(asserter, &results);
+    }
+
+    /// Encodes the return value of a typed sol call
+    pub(crate) fn ret<C: SolCall>(value: &C::Return) -> Option<Bytes> {
+        Some(Bytes::from(C::abi_encode_returns(value)))
+    }
+
+    #[tokio::test]
+    async fn batches_keep_call_order() {
+        let (asserter, provider) = mocked();
+        let mut multicall = Multicall::new(provider);
+        multicall.batch_size = 2;
+
+        let data = |b: u8| Some(Bytes::from(vec![b]));
+        push_aggregate(
+            &asserter,
+            vec![
+                data(1),
+                None,
+            ],
+        );
+        push_aggregate(&asserter, vec![data(3)]);
+
+        let calls = (0..3)
+            .map(|_| (Address::ZERO, Bytes::new()))
+            .collect();
+        let results = multicall.try_aggregate(calls).await.unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                data(1),
+                None,
+                data(3)
+            ]
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    time::{Duration, SystemTime},
+};
 
 use alloy::{
     primitives::{aliases::I24, Address, U256},
@@ -8,10 +11,16 @@ use alloy_contract::EthCall;
 use futures::future::join_all;
 
 use crate::{
-    any_trade::UniTrade,
+    err::{
+        CommitError,
+        StateError,
+        TradeError,
+    },
+    price::Price,
     sol_types::{StateView::getTickInfoCall, V3Pool::ticksCall},
     v3_base::{
-        bitmap_math, tick_math,
+        bitmap_math,
+        states::TradeReceipt,
         ticks::{Tick, Ticks},
     },
 };
@@ -21,21 +30,169 @@ pub trait UniPool {
         &mut self,
         amount: U256,
         from0: bool,
-    ) -> Result<UniTrade, crate::err::TradeError>;
+    ) -> Result<TradeReceipt, TradeError>;
 
     async fn sync(&mut self) -> Result<(), ()>;
     fn create_sync_call(&self) -> Vec<TransactionRequest>;
     fn decode_sync_result(&mut self, responses: Vec<EthCallResponse>) -> Result<(), ()>;
 
+    /// Moves the pool to the state a receipt of `trade` ends with. Fails when the
+    /// receipt belongs to another pool, or when the pool state is no longer the
+    /// receipt's `checkpoint`.
+    fn apply_trade(&mut self, receipt: &TradeReceipt) -> Result<(), StateError>;
+
+    /// Quotes a trade and commits it
+    fn trade_and_commit(
+        &mut self,
+        amount: U256,
+        from0: bool,
+    ) -> Result<TradeReceipt, CommitError> {
+        let receipt = self.trade(amount, from0)?;
+        self.apply_trade(&receipt)?;
+        Ok(receipt)
+    }
+
+    fn sync_status(&self) -> &SyncStatus;
+    fn sync_status_mut(&mut self) -> &mut SyncStatus;
+
+    /// `trade` that refuses state last synced more than `max_age_blocks` before
+    /// `head`, or at an unknown block. Concentrated pools also refuse a tick set
+    /// older than `max_tick_age_blocks`, the same limits `ResyncPolicy` applies.
+    #[allow(clippy::result_large_err)]
+    fn trade_checked(
+        &mut self,
+        amount: U256,
+        from0: bool,
+        head: u64,
+        max_age_blocks: u64,
+        max_tick_age_blocks: u64,
+    ) -> Result<TradeReceipt, TradeError> {
+        let status = self.sync_status();
+        if status
+            .age_blocks(head)
+            .is_none_or(|age| age > max_age_blocks)
+        {
+            return Err(TradeError::Stale {
+                synced_block: status.last_synced_block,
+                head,
+            });
+        }
+        let concentrated =
+            matches!(self.checkpoint(), PoolCheckpoint::Concentrated { .. });
+        if concentrated
+            && status
+                .ticks_age_blocks(head)
+                .is_none_or(|age| age > max_tick_age_blocks)
+        {
+            return Err(TradeError::StaleTicks {
+                synced_block: status.ticks_synced_block,
+                head,
+            });
+        }
+        self.trade(amount, from0)
+    }
+
+    fn checkpoint(&self) -> PoolCheckpoint;
+    /// Restores a checkpoint taken on this pool, fails for a checkpoint of another
+    /// pool kind
+    fn rollback(&mut self, checkpoint: PoolCheckpoint) -> Result<(), StateError>;
+
     fn get_a(&self) -> &Address;
     fn get_b(&self) -> &Address;
-    fn get_price(&self) -> U256;
+    fn get_price(&self) -> Price;
     fn get_liquidity(&self) -> U256;
 }
 
+/// The part of a pool state a swap changes. Ticks only move with liquidity events, so
+/// concentrated liquidity checkpoints leave them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolCheckpoint {
+    V2 {
+        reserves0: U256,
+        reserves1: U256,
+    },
+    Concentrated {
+        tick: I24,
+        liquidity: U256,
+        x96price: U256,
+    },
+}
+
+impl PoolCheckpoint {
+    /// Price of the checkpointed state, as `UniPool::get_price` reports it
+    pub fn price(&self) -> Price {
+        match self {
+            PoolCheckpoint::V2 {
+                reserves0,
+                reserves1,
+            } => Price::from_amounts(*reserves0, *reserves1).unwrap_or_default(),
+            PoolCheckpoint::Concentrated {
+                x96price,
+                ..
+            } => Price::from_sqrt_x96(*x96price),
+        }
+    }
+
+    /// Liquidity of the checkpointed state, as `UniPool::get_liquidity` reports it
+    pub fn liquidity(&self) -> U256 {
+        match self {
+            PoolCheckpoint::V2 {
+                reserves0,
+                reserves1,
+            } => reserves0 + reserves1,
+            PoolCheckpoint::Concentrated {
+                liquidity,
+                ..
+            } => *liquidity,
+        }
+    }
+}
+
+/// When a pool state and its tick set were last brought up to date. Blocks are only
+/// known when the sync ran through the registry or event application.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SyncStatus {
+    pub last_synced_block: Option<u64>,
+    pub last_synced_at: Option<SystemTime>,
+    pub ticks_synced_block: Option<u64>,
+    pub ticks_synced_at: Option<SystemTime>,
+    /// Relative price change of the last update, `|new / old - 1|`
+    pub volatility: f64,
+}
+
+impl SyncStatus {
+    pub fn mark_synced(&mut self, block: Option<u64>) {
+        self.last_synced_block = block.or(self.last_synced_block);
+        self.last_synced_at = Some(SystemTime::now());
+    }
+
+    pub fn mark_ticks_synced(&mut self, block: Option<u64>) {
+        self.ticks_synced_block = block.or(self.ticks_synced_block);
+        self.ticks_synced_at = Some(SystemTime::now());
+    }
+
+    /// Blocks since the last sync, `None` when the sync block is unknown
+    pub fn age_blocks(&self, head: u64) -> Option<u64> {
+        Some(head.saturating_sub(self.last_synced_block?))
+    }
+
+    pub fn ticks_age_blocks(&self, head: u64) -> Option<u64> {
+        Some(head.saturating_sub(self.ticks_synced_block?))
+    }
+
+    /// Time since the last sync, `None` when never synced
+    pub fn age(&self) -> Option<Duration> {
+        Some(self.last_synced_at?.elapsed().unwrap_or_default())
+    }
+
+    pub fn ticks_age(&self) -> Option<Duration> {
+        Some(self.ticks_synced_at?.elapsed().unwrap_or_default())
+    }
+}
+
 pub trait ConcentratedLiquidity: UniPool {
     async fn sync_ticks(&mut self) -> Result<(), ()> {
-        let Some(tick) = tick_math::tick_from_price(self.get_price()) else {
+        let Some(tick) = self.get_price().to_tick() else {
             return Err(());
         };
         let tick_spacing = self.get_tick_spacing();
@@ -47,6 +204,7 @@ pub trait ConcentratedLiquidity: UniPool {
             word_pos + 1,
         ];
         let mut ticks = Vec::<I24>::new();
+        let mut complete = true;
         for pos in words_pos {
             match self.request_word(pos).await {
                 Ok(w) => {
@@ -54,7 +212,7 @@ pub trait ConcentratedLiquidity: UniPool {
                         bitmap_math::extract_ticks_from_bitmap(w, pos, tick_spacing);
                     ticks.append(&mut t);
                 }
-                Err(_) => (),
+                Err(_) => complete = false,
             }
         }
         let mut futs = Vec::new();
@@ -65,11 +223,19 @@ pub trait ConcentratedLiquidity: UniPool {
         for r in join_all(futs).await {
             match r {
                 Ok(ok) => tks.push(ok),
-                Err(_) => (),
+                Err(_) => complete = false,
             }
         }
 
-        self.get_mut_ticks().insert_ticks(tks);
+        let range = bitmap_math::word_range(word_pos - 1, tick_spacing)
+            .zip(bitmap_math::word_range(word_pos + 1, tick_spacing));
+        let ticks = self.get_mut_ticks();
+        ticks.insert_ticks(tks);
+        // a missing word or tick leaves a hole, so the range is only known when complete
+        if let (true, Some(((lower, _), (_, upper)))) = (complete, range) {
+            ticks.set_synced_range(lower, upper);
+        }
+        self.sync_status_mut().mark_ticks_synced(None);
 
         Ok(())
     }
@@ -85,3 +251,129 @@ pub enum UniTickCall {
     V3(EthCall<'static, PhantomData<ticksCall>, alloy::network::Ethereum>),
     V4(EthCall<'static, PhantomData<getTickInfoCall>, alloy::network::Ethereum>),
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::aliases::U24;
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{
+        any_pool::{
+            AnyPool,
+            V4Key,
+        },
+        routing::tests::{
+            pair,
+            E18,
+        },
+        v3_pool::V3Pool,
+    };
+
+    fn v3<P: alloy_provider::Provider>(provider: P) -> AnyPool<P> {
+        let key = V4Key {
+            currency0: Address::repeat_byte(1),
+            currency1: Address::repeat_byte(2),
+            fee: U24::from(3000),
+            tickspacing: I24::try_from(10).unwrap(),
+            ..Default::default()
+        };
+        let mut v3 =
+            V3Pool::new_from_key(Address::repeat_byte(3), provider, Address::ZERO, key)
+                .unwrap();
+        v3.state.x96price = U256::ONE << 96;
+        v3.state.liquidity = U256::from(1_000 * E18);
+        v3.state.ticks.insert_ticks(vec![
+            Tick {
+                tick: I24::try_from(-50).unwrap(),
+                liquidity_net: Some(1_000 * E18 as i128),
+            },
+            Tick {
+                tick: I24::try_from(50).unwrap(),
+                liquidity_net: Some(-(1_000 * E18 as i128)),
+            },
+        ]);
+        AnyPool::V3(v3)
+    }
+
+    #[test]
+    fn commit_and_rollback() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let v2 = pair(provider.clone(), 1, 2, 1_000, 2_000);
+        let v3 = v3(provider);
+
+        let mut pools = [
+            v2, v3,
+        ];
+        let mut receipts = Vec::new();
+        for pool in &mut pools {
+            let before = pool.checkpoint();
+            let receipt = pool
+                .trade_and_commit(U256::from(E18), true)
+                .unwrap();
+            assert_eq!(receipt.checkpoint, before);
+            assert_ne!(pool.checkpoint(), before);
+            assert_eq!(pool.get_price(), receipt.price_after);
+
+            // the receipt was quoted on the state before the commit
+            assert_eq!(pool.apply_trade(&receipt), Err(StateError::Changed));
+
+            pool.rollback(before).unwrap();
+            assert_eq!(pool.checkpoint(), before);
+            pool.apply_trade(&receipt).unwrap();
+            receipts.push(receipt);
+        }
+
+        // checkpoints and receipts of another pool kind are refused
+        let [v2, v3] = &mut pools;
+        assert_eq!(v2.rollback(v3.checkpoint()), Err(StateError::WrongPool));
+        assert_eq!(v3.rollback(v2.checkpoint()), Err(StateError::WrongPool));
+        assert_eq!(v2.apply_trade(&receipts[1]), Err(StateError::WrongPool));
+        assert!(matches!(
+            v3.trade_and_commit(U256::MAX, true),
+            Err(CommitError::Trade(_))
+        ));
+    }
+
+    #[test]
+    fn trade_checked_refuses_old_state() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let amount = U256::from(E18);
+        let mut v2 = pair(provider.clone(), 1, 2, 1_000, 2_000);
+        let mut v3 = v3(provider);
+
+        // never synced at a known block
+        assert!(matches!(
+            v2.trade_checked(amount, true, 100, 10, 10),
+            Err(TradeError::Stale {
+                synced_block: None,
+                head: 100,
+            })
+        ));
+
+        v2.sync_status_mut().mark_synced(Some(95));
+        assert!(v2
+            .trade_checked(amount, true, 100, 10, 10)
+            .is_ok());
+        assert!(matches!(
+            v2.trade_checked(amount, true, 106, 10, 10),
+            Err(TradeError::Stale { .. })
+        ));
+
+        // fresh slot0 is not enough when the ticks are old
+        v3.sync_status_mut().mark_synced(Some(100));
+        v3.sync_status_mut().mark_ticks_synced(Some(50));
+        assert!(matches!(
+            v3.trade_checked(amount, true, 100, 10, 20),
+            Err(TradeError::StaleTicks {
+                synced_block: Some(50),
+                head: 100,
+            })
+        ));
+        assert!(v3
+            .trade_checked(amount, true, 100, 10, 50)
+            .is_ok());
+    }
+}
@@ -0,0 +1,111 @@
+use alloy::primitives::{address, aliases::U24, b256, keccak256, Address, B256};
+use alloy_sol_types::SolValue;
+
+use crate::{any_pool::V4Key, sol_types::PoolKey};
+
+/// Address that runs CREATE2 for a protocol's pools and the hash of the pool init code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Create2Deployment {
+    pub deployer: Address,
+    pub init_code_hash: B256,
+}
+
+pub const UNISWAP_V2_ETHEREUM: Create2Deployment = Create2Deployment {
+    deployer: address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+    init_code_hash: b256!(
+        "96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+    ),
+};
+
+pub const UNISWAP_V3_ETHEREUM: Create2Deployment = Create2Deployment {
+    deployer: address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+    init_code_hash: b256!(
+        "e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54"
+    ),
+};
+
+pub const UNISWAP_V3_BSC: Create2Deployment = Create2Deployment {
+    deployer: address!("dB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7"),
+    init_code_hash: b256!(
+        "e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54"
+    ),
+};
+
+pub const PANCAKE_V2_BSC: Create2Deployment = Create2Deployment {
+    deployer: address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+    init_code_hash: b256!(
+        "00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5"
+    ),
+};
+
+/// PancakeSwap V3 pools are created by the pool deployer, not by the factory
+pub const PANCAKE_V3_BSC: Create2Deployment = Create2Deployment {
+    deployer: address!("41ff9AA7e16B8B1a8a8dc4f0eFacd93D02d071c9"),
+    init_code_hash: b256!(
+        "6ce8eb472fa82df5469c6ab6d485f17c3ad13c8cd7af59b3d4a8026c5ce0f7e2"
+    ),
+};
+
+/// Orders two tokens the way factories do for `token0`/`token1`
+pub fn sort_tokens(token_a: Address, token_b: Address) -> (Address, Address) {
+    if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    }
+}
+
+/// V2 pair address, salt is `keccak256(abi.encodePacked(token0, token1))`
+pub fn v2_pair_address(
+    deployment: &Create2Deployment,
+    token_a: Address,
+    token_b: Address,
+) -> Address {
+    let (token0, token1) = sort_tokens(token_a, token_b);
+    let salt = keccak256((token0, token1).abi_encode_packed());
+
+    deployment
+        .deployer
+        .create2(salt, deployment.init_code_hash)
+}
+
+/// V3 pool address, salt is `keccak256(abi.encode(token0, token1, fee))`
+pub fn v3_pool_address(
+    deployment: &Create2Deployment,
+    token_a: Address,
+    token_b: Address,
+    fee: U24,
+) -> Address {
+    let (token0, token1) = sort_tokens(token_a, token_b);
+    let salt = keccak256((token0, token1, fee).abi_encode());
+
+    deployment
+        .deployer
+        .create2(salt, deployment.init_code_hash)
+}
+
+/// V4 `PoolId`, the hash of the abi encoded `PoolKey`
+pub fn v4_pool_id(key: &V4Key) -> B256 {
+    let key: PoolKey = (*key).into();
+    keccak256(key.abi_encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    #[test]
+    fn uniswap_usdc_weth() {
+        assert_eq!(
+            v2_pair_address(&UNISWAP_V2_ETHEREUM, WETH, USDC),
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")
+        );
+        assert_eq!(
+            v3_pool_address(&UNISWAP_V3_ETHEREUM, USDC, WETH, U24::from(500)),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use alloy::primitives::{aliases::I24, U256, U512};
+use serde::{Deserialize, Serialize};
+
+use crate::v3_base::tick_math::{price_from_tick, tick_from_price};
+
+/// Token1 per token0 in raw token units as a Q320.192 number, so squaring a
+/// sqrtPriceX96 is exact
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub struct Price {
+    pub x192: U512,
+}
+
+impl Price {
+    pub const ONE: Price = Price {
+        x192: U512::from_limbs([
+            0, 0, 0, 1, 0, 0, 0, 0,
+        ]),
+    };
+
+    pub fn from_sqrt_x96(sqrt_x96: U256) -> Self {
+        let sqrt = U512::from(sqrt_x96);
+        Self {
+            x192: sqrt * sqrt,
+        }
+    }
+
+    /// `amount1 / amount0`, e.g. from V2 reserves. `None` when `amount0` is zero.
+    pub fn from_amounts(amount0: U256, amount1: U256) -> Option<Self> {
+        if amount0.is_zero() {
+            return None;
+        }
+        Some(Self {
+            x192: (U512::from(amount1) << 192) / U512::from(amount0),
+        })
+    }
+
+    pub fn from_tick(tick: I24) -> Option<Self> {
+        price_from_tick(tick).map(Self::from_sqrt_x96)
+    }
+
+    /// Parses a human price like `"1834.52"` of one whole token0 in whole token1
+    pub fn from_decimal_str(value: &str, decimals0: u8, decimals1: u8) -> Option<Self> {
+        let (int_part, frac_part) = value
+            .trim()
+            .split_once('.')
+            .unwrap_or((value.trim(), ""));
+        let digits = format!("{}{}", int_part, frac_part);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let ten = U512::from(10);
+        let n = U512::from_str_radix(&digits, 10).ok()?;
+        let num = n
+            .checked_mul(ten.checked_pow(U512::from(decimals1))?)?
+            .checked_shl(192)?;
+        let den = ten
+            .checked_pow(U512::from(frac_part.len()))?
+            .checked_mul(ten.checked_pow(U512::from(decimals0))?)?;
+
+        Some(Self {
+            x192: num / den,
+        })
+    }
+
+    /// Rounded down to a valid sqrtPriceX96
+    pub fn to_sqrt_x96(&self) -> U256 {
+        U256::saturating_from(self.x192.root(2))
+    }
+
+    /// The tick whose price range holds this price
+    pub fn to_tick(&self) -> Option<I24> {
+        tick_from_price(self.to_sqrt_x96())
+    }
+
+    /// Token0 per token1
+    pub fn invert(&self) -> Option<Self> {
+        if self.x192.is_zero() {
+            return None;
+        }
+        Some(Self {
+            x192: (U512::ONE << 384) / self.x192,
+        })
+    }
+
+    /// Raw ratio as a float, for logs and heuristics
+    pub fn to_f64(&self) -> f64 {
+        f64::from(self.x192) / 2f64.powi(192)
+    }
+
+    /// Human price of one whole token0 in whole token1 with `precision` fractional
+    /// digits, rounded to nearest
+    pub fn to_decimal_string(
+        &self,
+        decimals0: u8,
+        decimals1: u8,
+        precision: u8,
+    ) -> Option<String> {
+        let ten = U512::from(10);
+        let num = self.x192.checked_mul(
+            ten.checked_pow(U512::from(decimals0 as u32 + precision as u32))?,
+        )?;
+        let scaled = num / ten.checked_pow(U512::from(decimals1))?;
+        let scaled = (scaled + (U512::ONE << 191)) >> 192;
+
+        Some(format_fixed(scaled, precision))
+    }
+}
+
+/// Renders `value / 10^precision` with exactly `precision` fractional digits
+pub(crate) fn format_fixed(value: U512, precision: u8) -> String {
+    let precision = precision as usize;
+    let digits = format!("{:0>width$}", value, width = precision + 1);
+    if precision == 0 {
+        return digits;
+    }
+    let (int_part, frac_part) = digits.split_at(digits.len() - precision);
+    format!("{}.{}", int_part, frac_part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_round_trip() {
+        // WETH (18) / USDC (6) pool where token0 is USDC
+        let price = Price::from_decimal_str("0.0005", 6, 18).unwrap();
+        assert_eq!(price.to_decimal_string(6, 18, 4).unwrap(), "0.0005");
+        assert_eq!(
+            price
+                .invert()
+                .unwrap()
+                .to_decimal_string(18, 6, 2)
+                .unwrap(),
+            "2000.00"
+        );
+
+        let one = Price::from_sqrt_x96(U256::ONE << 96);
+        assert_eq!(one, Price::ONE);
+        assert_eq!(one.to_tick(), Some(I24::ZERO));
+        assert_eq!(
+            Price::from_amounts(U256::from(4), U256::from(8))
+                .unwrap()
+                .to_f64(),
+            2.0
+        );
+    }
+}
@@ -0,0 +1,1019 @@
+use std::collections::{HashMap, VecDeque};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{
+        aliases::{I24, U24},
+        Address, B256, U256,
+    },
+    rpc::types::{EthCallResponse, Filter, Log},
+};
+use alloy_provider::Provider;
+use alloy_sol_types::SolEvent;
+use futures::{stream, Stream};
+use tokio::sync::broadcast;
+
+use crate::{
+    any_pool::AnyPool,
+    err::{JournalError, SyncError},
+    pool::{PoolCheckpoint, UniPool},
+    price::Price,
+    sol_types::{
+        IPoolManager::{ModifyLiquidity, Swap as V4Swap},
+        IUniswapV2Pair::Sync,
+        V3Pool::{Burn, Mint, Swap as V3Swap},
+    },
+    v3_base::{
+        oracle::{ObservationBuffer, ObservationUndo},
+        ticks::Tick,
+        v3_state::V3State,
+    },
+};
+
+/// Blocks kept for rollback when not configured, deeper than BSC's usual reorgs
+pub const DEFAULT_JOURNAL_DEPTH: usize = 64;
+/// Updates a subscriber can fall behind by before it starts missing them
+pub const UPDATE_CHANNEL_CAPACITY: usize = 4096;
+
+/// Registry key of a pool, its contract for V2/V3 and its id for V4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PoolRef {
+    Address(Address),
+    V4(B256),
+}
+
+impl<P: Provider> From<&AnyPool<P>> for PoolRef {
+    fn from(pool: &AnyPool<P>) -> Self {
+        match pool {
+            AnyPool::V2(v2_pool) => PoolRef::Address(v2_pool.key.address),
+            AnyPool::V3(v3_pool) => PoolRef::Address(*v3_pool.contract.address()),
+            AnyPool::V4(v4_pool) => PoolRef::V4(v4_pool.id),
+        }
+    }
+}
+
+/// Price and liquidity change of one pool. Event application sends one update per
+/// pool and block, with the state before the block as `old_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolUpdate {
+    pub pool: PoolRef,
+    /// Block the change belongs to, the head at the time for syncs
+    pub block: Option<u64>,
+    pub old_price: Price,
+    pub new_price: Price,
+    pub old_liquidity: U256,
+    pub new_liquidity: U256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRef {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+}
+
+/// State of one pool before a block touched it
+#[derive(Debug, Clone)]
+struct PoolUndo {
+    pool: PoolRef,
+    checkpoint: PoolCheckpoint,
+    /// Ticks changed by the block and their value before it, `None` if they were
+    /// not present
+    ticks: Vec<(I24, Option<Tick>)>,
+    /// First observation the block wrote
+    observation: Option<ObservationUndo>,
+}
+
+#[derive(Debug, Clone)]
+struct BlockDiff {
+    block: BlockRef,
+    undo: Vec<PoolUndo>,
+}
+
+/// Pools kept in step with the chain through their logs. Every applied block records
+/// what it changed, so a reorg rolls back to the common ancestor and replays the
+/// canonical blocks instead of resyncing every pool.
+pub struct PoolRegistry<P: Provider> {
+    pub provider: P,
+    /// V4 PoolManager contracts whose logs are applied
+    pub pool_managers: Vec<Address>,
+    /// Blocks kept in the journal, a reorg deeper than this needs a full resync
+    pub max_depth: usize,
+    pools: HashMap<PoolRef, AnyPool<P>>,
+    journal: VecDeque<BlockDiff>,
+    updates: broadcast::Sender<PoolUpdate>,
+    observations: HashMap<PoolRef, ObservationBuffer>,
+}
+
+impl<P: Provider> PoolRegistry<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            pool_managers: Vec::new(),
+            max_depth: DEFAULT_JOURNAL_DEPTH,
+            pools: HashMap::new(),
+            journal: VecDeque::new(),
+            updates: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+            observations: HashMap::new(),
+        }
+    }
+
+    /// Receiver of every state change from syncs, applied blocks and reorg rollbacks.
+    /// Simulations through checkpoints don't send updates.
+    pub fn subscribe(&self) -> broadcast::Receiver<PoolUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// `subscribe` as a `Stream`, updates missed by a lagging consumer are skipped
+    pub fn updates(&self) -> impl Stream<Item = PoolUpdate> {
+        stream::unfold(self.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(update) => return Some((update, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Resyncs one pool and its ticks from the node
+    pub async fn sync_pool(&mut self, pool: &PoolRef) -> Result<(), SyncError> {
+        let block = self.head().map(|b| b.number);
+        self.resync_pool(pool, true, block).await
+    }
+
+    /// Resyncs one pool, and its ticks when `ticks` is set, recording `block` as the
+    /// block the node answered at
+    pub async fn resync_pool(
+        &mut self,
+        pool: &PoolRef,
+        ticks: bool,
+        block: Option<u64>,
+    ) -> Result<(), SyncError> {
+        let Some(p) = self.pools.get_mut(pool) else {
+            return Err(SyncError::UnknownPool);
+        };
+        let before = p.checkpoint();
+        let result = p
+            .resync(ticks)
+            .await
+            .map_err(|_| SyncError::Failed);
+        if result.is_ok() {
+            p.sync_status_mut().mark_synced(block);
+            if ticks {
+                p.sync_status_mut().mark_ticks_synced(block);
+            }
+        }
+        self.notify(*pool, before, block);
+        result
+    }
+
+    /// Applies batched `create_sync_call` responses to one pool
+    pub fn decode_sync_result(
+        &mut self,
+        pool: &PoolRef,
+        responses: Vec<EthCallResponse>,
+    ) -> Result<(), SyncError> {
+        let block = self.head().map(|b| b.number);
+        let Some(p) = self.pools.get_mut(pool) else {
+            return Err(SyncError::UnknownPool);
+        };
+        let before = p.checkpoint();
+        let result = p
+            .decode_sync_result(responses)
+            .map_err(|_| SyncError::Failed);
+        if result.is_ok() {
+            p.sync_status_mut().mark_synced(block);
+        }
+        self.notify(*pool, before, block);
+        result
+    }
+
+    /// Sends an update when `pool` moved away from `before` and records the price
+    /// change as the pool volatility
+    fn notify(&mut self, pool: PoolRef, before: PoolCheckpoint, block: Option<u64>) {
+        let Some(p) = self.pools.get_mut(&pool) else {
+            return;
+        };
+        let after = p.checkpoint();
+        if before.price() == after.price() && before.liquidity() == after.liquidity() {
+            return;
+        }
+        let (old, new) = (before.price().to_f64(), after.price().to_f64());
+        if old > 0.0 {
+            p.sync_status_mut().volatility = (new / old - 1.0).abs();
+        }
+        // no receivers is not an error
+        let _ = self.updates.send(PoolUpdate {
+            pool,
+            block,
+            old_price: before.price(),
+            new_price: after.price(),
+            old_liquidity: before.liquidity(),
+            new_liquidity: after.liquidity(),
+        });
+    }
+
+    pub fn insert(&mut self, pool: AnyPool<P>) -> PoolRef {
+        let key = PoolRef::from(&pool);
+        self.pools.insert(key, pool);
+        key
+    }
+
+    pub fn remove(&mut self, pool: &PoolRef) -> Option<AnyPool<P>> {
+        self.pools.remove(pool)
+    }
+
+    pub fn get(&self, pool: &PoolRef) -> Option<&AnyPool<P>> {
+        self.pools.get(pool)
+    }
+
+    pub fn get_mut(&mut self, pool: &PoolRef) -> Option<&mut AnyPool<P>> {
+        self.pools.get_mut(pool)
+    }
+
+    /// Keeps `buffer` current from the swaps and in-range position changes of a V3
+    /// pool, as read by `V3Pool::observation_buffer`. Only logs carrying their
+    /// `block_timestamp` write, the caller writes the ones that don't.
+    pub fn track_observations(&mut self, pool: PoolRef, buffer: ObservationBuffer) {
+        self.observations.insert(pool, buffer);
+    }
+
+    pub fn observations(&self, pool: &PoolRef) -> Option<&ObservationBuffer> {
+        self.observations.get(pool)
+    }
+
+    /// First V2 pool (`fee` is `None`) or V3 pool with `fee` between two tokens,
+    /// optionally restricted to pools of `factory`
+    pub fn find(
+        &self,
+        token_a: Address,
+        token_b: Address,
+        fee: Option<U24>,
+        factory: Option<Address>,
+    ) -> Option<PoolRef> {
+        let holds = |t0: &Address, t1: &Address| {
+            (*t0 == token_a && *t1 == token_b) || (*t0 == token_b && *t1 == token_a)
+        };
+        self.pools.iter().find_map(|(key, pool)| {
+            let found = match (pool, fee) {
+                (AnyPool::V2(v2_pool), None) => {
+                    holds(&v2_pool.key.token0, &v2_pool.key.token1)
+                        && factory.is_none_or(|f| f == v2_pool.factory)
+                }
+                (AnyPool::V3(v3_pool), Some(fee)) => {
+                    holds(&v3_pool.key.currency0, &v3_pool.key.currency1)
+                        && v3_pool.key.fee == fee
+                        && factory.is_none_or(|f| f == v3_pool.factory)
+                }
+                _ => false,
+            };
+            found.then_some(*key)
+        })
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = (&PoolRef, &AnyPool<P>)> {
+        self.pools.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Latest applied block
+    pub fn head(&self) -> Option<BlockRef> {
+        self.journal.back().map(|d| d.block)
+    }
+
+    /// Forgets the journal, e.g. after resyncing every pool at a new block
+    pub fn reset(&mut self, head: BlockRef) {
+        self.journal.clear();
+        self.journal.push_back(BlockDiff {
+            block: head,
+            undo: Vec::new(),
+        });
+    }
+
+    /// Log filter over every pool contract and PoolManager of the registry
+    pub fn filter(&self) -> Filter {
+        let mut addresses: Vec<Address> = self
+            .pools
+            .keys()
+            .filter_map(|k| match k {
+                PoolRef::Address(address) => Some(*address),
+                PoolRef::V4(_) => None,
+            })
+            .chain(self.pool_managers.iter().copied())
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+
+        Filter::new()
+            .address(addresses)
+            .event_signature(vec![
+                Sync::SIGNATURE_HASH,
+                V3Swap::SIGNATURE_HASH,
+                Mint::SIGNATURE_HASH,
+                Burn::SIGNATURE_HASH,
+                V4Swap::SIGNATURE_HASH,
+                ModifyLiquidity::SIGNATURE_HASH,
+            ])
+    }
+
+    /// Applies the logs of `block`. A block that doesn't extend the head first rolls
+    /// back to its parent, which has to be in the journal.
+    pub fn apply_block(
+        &mut self,
+        block: BlockRef,
+        logs: &[Log],
+    ) -> Result<(), JournalError> {
+        if self
+            .journal
+            .iter()
+            .any(|d| d.block.hash == block.hash)
+        {
+            return Ok(());
+        }
+        if let Some(head) = self.head() {
+            if head.hash != block.parent_hash {
+                self.rollback_to(block.parent_hash)?;
+            }
+        }
+
+        let mut diff = BlockDiff {
+            block,
+            undo: Vec::new(),
+        };
+        for log in logs {
+            if log.removed || log.block_hash.is_some_and(|h| h != block.hash) {
+                continue;
+            }
+            self.apply_log(log, &mut diff.undo);
+        }
+        for undo in &diff.undo {
+            self.notify(undo.pool, undo.checkpoint, Some(block.number));
+        }
+        // the filter covers every pool, so the ones synced up to the parent stay
+        // current through this block. Unsynced pools or ones that missed blocks don't.
+        for pool in self.pools.values_mut() {
+            let is_v2 = matches!(pool, AnyPool::V2(_));
+            let status = pool.sync_status_mut();
+            if contiguous(status.last_synced_block, block.number) {
+                status.mark_synced(Some(block.number));
+            }
+            if !is_v2 && contiguous(status.ticks_synced_block, block.number) {
+                status.mark_ticks_synced(Some(block.number));
+            }
+        }
+
+        self.journal.push_back(diff);
+        while self.journal.len() > self.max_depth.max(1) {
+            self.journal.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Undoes journaled blocks until `hash` is the head. Returns how many blocks were
+    /// rolled back.
+    pub fn rollback_to(&mut self, hash: B256) -> Result<usize, JournalError> {
+        let Some(pos) = self
+            .journal
+            .iter()
+            .rposition(|d| d.block.hash == hash)
+        else {
+            return Err(JournalError::ReorgTooDeep);
+        };
+
+        let depth = self.journal.len() - pos - 1;
+        for _ in 0..depth {
+            let Some(diff) = self.journal.pop_back() else {
+                break;
+            };
+            let parent = diff.block.number.checked_sub(1);
+            for undo in diff.undo.into_iter().rev() {
+                let pool = undo.pool;
+                let Some(before) = self.pools.get(&pool).map(|p| p.checkpoint()) else {
+                    continue;
+                };
+                self.restore(undo);
+                self.notify(pool, before, parent);
+            }
+        }
+        Ok(depth)
+    }
+
+    /// Brings the registry to block `number` of the canonical chain. When the chain
+    /// reorganized, the journal is rolled back to the last block both chains share
+    /// and every canonical block after it is replayed from its logs.
+    pub async fn follow(&mut self, number: u64) -> Result<(), JournalError> {
+        let Some(head) = self.head() else {
+            let block = self.fetch_block(number).await?;
+            let logs = self.fetch_logs(block.hash).await?;
+            return self.apply_block(block, &logs);
+        };
+
+        let mut ancestor = None;
+        let mut canonical = Vec::new();
+        for diff in self.journal.iter().rev() {
+            if diff.block.number > number {
+                continue;
+            }
+            let block = self.fetch_block(diff.block.number).await?;
+            if block.hash == diff.block.hash {
+                ancestor = Some(diff.block);
+                break;
+            }
+            canonical.push(block);
+        }
+        let Some(ancestor) = ancestor else {
+            return Err(JournalError::ReorgTooDeep);
+        };
+        if ancestor.hash != head.hash {
+            self.rollback_to(ancestor.hash)?;
+        }
+
+        canonical.reverse();
+        let mut next = ancestor.number + 1;
+        while next <= number {
+            let block = match canonical.first() {
+                Some(block) if block.number == next => canonical.remove(0),
+                _ => self.fetch_block(next).await?,
+            };
+            let logs = self.fetch_logs(block.hash).await?;
+            self.apply_block(block, &logs)?;
+            next += 1;
+        }
+        Ok(())
+    }
+
+    async fn fetch_block(&self, number: u64) -> Result<BlockRef, JournalError> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(number))
+            .await?
+            .ok_or(JournalError::MissingBlock(number))?;
+        Ok(BlockRef {
+            number,
+            hash: block.header.hash,
+            parent_hash: block.header.parent_hash,
+        })
+    }
+
+    async fn fetch_logs(&self, hash: B256) -> Result<Vec<Log>, JournalError> {
+        let filter = self.filter().at_block_hash(hash);
+        Ok(self.provider.get_logs(&filter).await?)
+    }
+
+    /// Applies one pool event, recording the prior state in `undo`. Logs of unknown
+    /// pools and other events are ignored.
+    fn apply_log(&mut self, log: &Log, undo: &mut Vec<PoolUndo>) -> bool {
+        let Some(topic) = log.topic0() else {
+            return false;
+        };
+
+        match *topic {
+            Sync::SIGNATURE_HASH => {
+                let Ok(event) = log.log_decode::<Sync>() else {
+                    return false;
+                };
+                let key = PoolRef::Address(log.address());
+                let Some(AnyPool::V2(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                record(undo, key, pool.checkpoint());
+                pool.state.reserves0 = U256::from(event.inner.data.reserve0);
+                pool.state.reserves1 = U256::from(event.inner.data.reserve1);
+                true
+            }
+            V3Swap::SIGNATURE_HASH => {
+                let Ok(event) = log.log_decode::<V3Swap>() else {
+                    return false;
+                };
+                let event = event.inner.data;
+                let key = PoolRef::Address(log.address());
+                let Some(AnyPool::V3(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                record(undo, key, pool.checkpoint());
+                observe(undo, key, self.observations.get_mut(&key), log, &pool.state);
+                pool.state.x96price = U256::from(event.sqrtPriceX96);
+                pool.state.liquidity = U256::from(event.liquidity);
+                pool.state.tick = event.tick;
+                true
+            }
+            Mint::SIGNATURE_HASH => {
+                let Ok(event) = log.log_decode::<Mint>() else {
+                    return false;
+                };
+                let event = event.inner.data;
+                let Ok(delta) = i128::try_from(event.amount) else {
+                    return false;
+                };
+                let key = PoolRef::Address(log.address());
+                let Some(AnyPool::V3(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                let tick = pool.state.tick;
+                if event.tickLower <= tick && tick < event.tickUpper {
+                    observe(undo, key, self.observations.get_mut(&key), log, &pool.state);
+                }
+                modify(
+                    undo,
+                    key,
+                    &mut pool.state,
+                    event.tickLower,
+                    event.tickUpper,
+                    delta,
+                )
+            }
+            Burn::SIGNATURE_HASH => {
+                let Ok(event) = log.log_decode::<Burn>() else {
+                    return false;
+                };
+                let event = event.inner.data;
+                let Ok(delta) = i128::try_from(event.amount) else {
+                    return false;
+                };
+                let key = PoolRef::Address(log.address());
+                let Some(AnyPool::V3(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                let tick = pool.state.tick;
+                if event.tickLower <= tick && tick < event.tickUpper {
+                    observe(undo, key, self.observations.get_mut(&key), log, &pool.state);
+                }
+                modify(
+                    undo,
+                    key,
+                    &mut pool.state,
+                    event.tickLower,
+                    event.tickUpper,
+                    -delta,
+                )
+            }
+            V4Swap::SIGNATURE_HASH => {
+                if !self.pool_managers.contains(&log.address()) {
+                    return false;
+                }
+                let Ok(event) = log.log_decode::<V4Swap>() else {
+                    return false;
+                };
+                let event = event.inner.data;
+                let key = PoolRef::V4(event.id);
+                let Some(AnyPool::V4(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                record(undo, key, pool.checkpoint());
+                pool.state.x96price = U256::from(event.sqrtPriceX96);
+                pool.state.liquidity = U256::from(event.liquidity);
+                pool.state.tick = event.tick;
+                true
+            }
+            ModifyLiquidity::SIGNATURE_HASH => {
+                if !self.pool_managers.contains(&log.address()) {
+                    return false;
+                }
+                let Ok(event) = log.log_decode::<ModifyLiquidity>() else {
+                    return false;
+                };
+                let event = event.inner.data;
+                let Ok(delta) = i128::try_from(event.liquidityDelta) else {
+                    return false;
+                };
+                let key = PoolRef::V4(event.id);
+                let Some(AnyPool::V4(pool)) = self.pools.get_mut(&key) else {
+                    return false;
+                };
+                modify(
+                    undo,
+                    key,
+                    &mut pool.state,
+                    event.tickLower,
+                    event.tickUpper,
+                    delta,
+                )
+            }
+            _ => false,
+        }
+    }
+
+    fn restore(&mut self, undo: PoolUndo) {
+        if let (Some(written), Some(buffer)) =
+            (undo.observation, self.observations.get_mut(&undo.pool))
+        {
+            buffer.undo(written);
+        }
+        let Some(pool) = self.pools.get_mut(&undo.pool) else {
+            return;
+        };
+        let _ = pool.rollback(undo.checkpoint);
+
+        let state = match pool {
+            AnyPool::V2(_) => return,
+            AnyPool::V3(v3_pool) => &mut v3_pool.state,
+            AnyPool::V4(v4_pool) => &mut v4_pool.state,
+        };
+        for (tick, before) in undo.ticks {
+            match before {
+                Some(t) => state.ticks.insert_ticks(vec![t]),
+                None => state.ticks.remove_tick(tick),
+            }
+        }
+    }
+}
+
+/// Whether a pool synced at `synced` is current up to the parent of block `number`
+fn contiguous(synced: Option<u64>, number: u64) -> bool {
+    synced.is_some_and(|synced| synced.saturating_add(1) >= number)
+}
+
+/// Index of the undo entry of `pool`, created with `checkpoint` on the first change
+/// in the block
+fn record(undo: &mut Vec<PoolUndo>, pool: PoolRef, checkpoint: PoolCheckpoint) -> usize {
+    if let Some(idx) = undo.iter().position(|u| u.pool == pool) {
+        return idx;
+    }
+    undo.push(PoolUndo {
+        pool,
+        checkpoint,
+        ticks: Vec::new(),
+        observation: None,
+    });
+    undo.len() - 1
+}
+
+/// Writes the oracle observation a V3 pool makes before a swap or an in-range
+/// position change, at the block time of `log`
+fn observe(
+    undo: &mut Vec<PoolUndo>,
+    pool: PoolRef,
+    buffer: Option<&mut ObservationBuffer>,
+    log: &Log,
+    state: &V3State,
+) {
+    let (Some(buffer), Some(time)) = (buffer, log.block_timestamp) else {
+        return;
+    };
+    let Ok(liquidity) = u128::try_from(state.liquidity) else {
+        return;
+    };
+    // the pool keeps `uint32(block.timestamp)`
+    if let Some(written) = buffer.write(time as u32, state.tick, liquidity) {
+        let idx = record(undo, pool, state.checkpoint());
+        undo[idx].observation.get_or_insert(written);
+    }
+}
+
+fn modify(
+    undo: &mut Vec<PoolUndo>,
+    pool: PoolRef,
+    state: &mut V3State,
+    lower: I24,
+    upper: I24,
+    delta: i128,
+) -> bool {
+    let idx = record(undo, pool, state.checkpoint());
+    for tick in [
+        lower, upper,
+    ] {
+        if !undo[idx].ticks.iter().any(|(t, _)| *t == tick) {
+            let before = state.ticks.get_tick(tick).ok();
+            undo[idx].ticks.push((tick, before));
+        }
+    }
+    state
+        .modify_liquidity(lower, upper, delta)
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::aliases::U112;
+    use alloy_provider::ProviderBuilder;
+    use alloy_sol_types::SolValue;
+
+    use super::*;
+    use crate::{
+        any_pool::V4Key,
+        v2_base::V2Key,
+        v2_pool::V2Pool,
+        v3_pool::V3Pool as V3PoolState,
+    };
+
+    fn log(address: Address, block: &BlockRef, data: alloy::primitives::LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address,
+                data,
+            },
+            block_hash: Some(block.hash),
+            block_number: Some(block.number),
+            block_timestamp: Some(1_000 + 12 * block.number),
+            ..Default::default()
+        }
+    }
+
+    fn tick(t: i32) -> I24 {
+        I24::try_from(t).unwrap()
+    }
+
+    fn sync_log(pair: Address, block: &BlockRef, reserve0: u64, reserve1: u64) -> Log {
+        let event = Sync {
+            reserve0: U112::from(reserve0),
+            reserve1: U112::from(reserve1),
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: pair,
+                data: event.encode_log_data(),
+            },
+            block_hash: Some(block.hash),
+            block_number: Some(block.number),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reorg_restores_parent_state() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let pair = Address::repeat_byte(7);
+        let key = V2Key {
+            fee: 3000,
+            address: pair,
+            token0: Address::repeat_byte(1),
+            token1: Address::repeat_byte(2),
+        };
+        let mut registry = PoolRegistry::new(provider.clone());
+        let pool =
+            registry.insert(V2Pool::new_from_key(key, Address::ZERO, provider).into());
+        let mut updates = registry.subscribe();
+
+        let b1 = BlockRef {
+            number: 1,
+            hash: B256::repeat_byte(1),
+            parent_hash: B256::ZERO,
+        };
+        let b2 = BlockRef {
+            number: 2,
+            hash: B256::repeat_byte(2),
+            parent_hash: b1.hash,
+        };
+        let b2_fork = BlockRef {
+            number: 2,
+            hash: B256::repeat_byte(3),
+            parent_hash: b1.hash,
+        };
+
+        registry
+            .apply_block(b1, &[sync_log(pair, &b1, 100, 200)])
+            .unwrap();
+        registry
+            .apply_block(b2, &[sync_log(pair, &b2, 110, 190)])
+            .unwrap();
+        registry.apply_block(b2_fork, &[]).unwrap();
+
+        let Some(AnyPool::V2(v2_pool)) = registry.get(&pool) else {
+            panic!("pool missing");
+        };
+        assert_eq!(v2_pool.state.reserves0, U256::from(100));
+        assert_eq!(registry.head(), Some(b2_fork));
+
+        // block 1, block 2 and the rollback of block 2
+        let blocks: Vec<Option<u64>> = std::iter::from_fn(|| updates.try_recv().ok())
+            .map(|u| u.block)
+            .collect();
+        assert_eq!(blocks, vec![Some(1), Some(2), Some(1)]);
+
+        let orphan = BlockRef {
+            number: 3,
+            hash: B256::repeat_byte(4),
+            parent_hash: B256::repeat_byte(9),
+        };
+        assert!(matches!(
+            registry.apply_block(orphan, &[]),
+            Err(JournalError::ReorgTooDeep)
+        ));
+    }
+
+    #[test]
+    fn blocks_advance_synced_pools() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let v2 = |byte: u8| {
+            let key = V2Key {
+                fee: 3000,
+                address: Address::repeat_byte(byte),
+                token0: Address::repeat_byte(1),
+                token1: Address::repeat_byte(2),
+            };
+            AnyPool::V2(V2Pool::new_from_key(key, Address::ZERO, provider.clone()))
+        };
+        let mut registry = PoolRegistry::new(provider.clone());
+        let unsynced = registry.insert(v2(7));
+        let synced = registry.insert(v2(8));
+        let behind = registry.insert(v2(9));
+        registry
+            .get_mut(&synced)
+            .unwrap()
+            .sync_status_mut()
+            .mark_synced(Some(1));
+        registry
+            .get_mut(&behind)
+            .unwrap()
+            .sync_status_mut()
+            .mark_synced(Some(0));
+
+        let b2 = BlockRef {
+            number: 2,
+            hash: B256::repeat_byte(2),
+            parent_hash: B256::repeat_byte(1),
+        };
+        registry.apply_block(b2, &[]).unwrap();
+
+        let block = |pool| {
+            registry
+                .get(&pool)
+                .unwrap()
+                .sync_status()
+                .last_synced_block
+        };
+        assert_eq!(block(unsynced), None);
+        assert_eq!(block(synced), Some(2));
+        assert_eq!(block(behind), Some(0));
+    }
+
+    #[test]
+    fn sync_results_send_updates() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let key = V2Key {
+            fee: 3000,
+            address: Address::repeat_byte(7),
+            token0: Address::repeat_byte(1),
+            token1: Address::repeat_byte(2),
+        };
+        let mut registry = PoolRegistry::new(provider.clone());
+        let pool =
+            registry.insert(V2Pool::new_from_key(key, Address::ZERO, provider).into());
+        let mut updates = registry.subscribe();
+        let reserves = |r0: u64, r1: u64| {
+            let value = (U256::from(r0), U256::from(r1), U256::ZERO).abi_encode_params();
+            vec![
+                EthCallResponse {
+                    value: Some(value.into()),
+                    error: None,
+                },
+            ]
+        };
+
+        registry
+            .decode_sync_result(&pool, reserves(100, 200))
+            .unwrap();
+        let update = updates.try_recv().unwrap();
+        let synced = registry.get(&pool).unwrap();
+        assert_eq!(update.pool, pool);
+        assert_eq!(update.new_liquidity, synced.checkpoint().liquidity());
+        assert!(synced.sync_status().last_synced_at.is_some());
+
+        // the same reserves again change nothing and send nothing
+        registry
+            .decode_sync_result(&pool, reserves(100, 200))
+            .unwrap();
+        assert!(updates.try_recv().is_err());
+
+        assert_eq!(
+            registry.decode_sync_result(&pool, Vec::new()),
+            Err(SyncError::Failed)
+        );
+        assert_eq!(
+            registry.decode_sync_result(&PoolRef::V4(B256::ZERO), reserves(1, 1)),
+            Err(SyncError::UnknownPool)
+        );
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn liquidity_events_roll_back() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let address = Address::repeat_byte(3);
+        let key = V4Key {
+            currency0: Address::repeat_byte(1),
+            currency1: Address::repeat_byte(2),
+            fee: U24::from(3000),
+            tickspacing: tick(10),
+            ..Default::default()
+        };
+        let mut v3 =
+            V3PoolState::new_from_key(address, provider.clone(), Address::ZERO, key)
+                .unwrap();
+        v3.state.x96price = U256::ONE << 96;
+        v3.state.liquidity = U256::from(1_000);
+        v3.state.ticks.insert_ticks(vec![
+            Tick {
+                tick: tick(-50),
+                liquidity_net: Some(1_000),
+            },
+            Tick {
+                tick: tick(50),
+                liquidity_net: Some(-1_000),
+            },
+        ]);
+        v3.state
+            .ticks
+            .set_synced_range(tick(-100), tick(100));
+        let before = v3.state.clone();
+
+        let mut registry = PoolRegistry::new(provider);
+        let pool = registry.insert(AnyPool::V3(v3));
+        let mut buffer = ObservationBuffer::new(1_000);
+        buffer.cardinality_next = 2;
+        registry.track_observations(pool, buffer.clone());
+        let state = |registry: &PoolRegistry<_>| match registry.get(&pool) {
+            Some(AnyPool::V3(v3_pool)) => v3_pool.state.clone(),
+            _ => panic!("pool missing"),
+        };
+
+        let b1 = BlockRef {
+            number: 1,
+            hash: B256::repeat_byte(1),
+            parent_hash: B256::ZERO,
+        };
+        let b2 = BlockRef {
+            number: 2,
+            hash: B256::repeat_byte(2),
+            parent_hash: b1.hash,
+        };
+        let b2_fork = BlockRef {
+            number: 2,
+            hash: B256::repeat_byte(3),
+            parent_hash: b1.hash,
+        };
+
+        registry.apply_block(b1, &[]).unwrap();
+        let mint = Mint {
+            sender: Address::ZERO,
+            owner: Address::ZERO,
+            tickLower: tick(-50),
+            tickUpper: tick(20),
+            amount: 300,
+            amount0: U256::ZERO,
+            amount1: U256::ZERO,
+        };
+        // the upper tick lies outside the synced range
+        let outside = Mint {
+            tickLower: tick(60),
+            tickUpper: tick(500),
+            ..mint.clone()
+        };
+        let burn = Burn {
+            owner: Address::ZERO,
+            tickLower: tick(-50),
+            tickUpper: tick(50),
+            amount: 100,
+            amount0: U256::ZERO,
+            amount1: U256::ZERO,
+        };
+        registry
+            .apply_block(
+                b2,
+                &[
+                    log(address, &b2, mint.encode_log_data()),
+                    log(address, &b2, outside.encode_log_data()),
+                    log(address, &b2, burn.encode_log_data()),
+                ],
+            )
+            .unwrap();
+
+        let after = state(&registry);
+        assert_eq!(after.liquidity, U256::from(1_200));
+        let net = |t: i32| {
+            after
+                .ticks
+                .get_tick(tick(t))
+                .ok()
+                .map(|t| t.liquidity_net)
+        };
+        assert_eq!(net(-50), Some(Some(1_200)));
+        assert_eq!(net(20), Some(Some(-300)));
+        assert_eq!(net(50), Some(Some(-900)));
+        assert_eq!(net(60), Some(Some(300)));
+        assert_eq!(net(500), Some(None));
+        // the in-range mint wrote the block's observation
+        let observations = registry.observations(&pool).unwrap();
+        assert_eq!(observations.index, 1);
+        assert_eq!(observations.latest().unwrap().block_timestamp, 1_024);
+
+        registry.apply_block(b2_fork, &[]).unwrap();
+        let restored = state(&registry);
+        assert_eq!(restored.checkpoint(), before.checkpoint());
+        assert_eq!(restored.ticks, before.ticks);
+        assert_eq!(registry.observations(&pool), Some(&buffer));
+    }
+}
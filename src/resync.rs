@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use alloy::primitives::{Bloom, BloomInput, U256};
+use alloy_provider::Provider;
+
+use crate::{
+    any_pool::AnyPool,
+    pool::UniPool,
+    registry::{PoolRef, PoolRegistry},
+};
+
+/// What a pool needs to be current again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SyncAction {
+    State,
+    /// State and tick set, for concentrated liquidity pools
+    StateAndTicks,
+}
+
+/// Pools with at least `min_liquidity`, as `UniPool::get_liquidity` reports it, are
+/// resynced after `max_age_blocks`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityTier {
+    pub min_liquidity: U256,
+    pub max_age_blocks: u64,
+}
+
+/// When a pool counts as stale. Every rule that applies can trigger a resync, a pool
+/// whose sync block is unknown is always due.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResyncPolicy {
+    /// Age in blocks for pools no tier matches
+    pub max_age_blocks: u64,
+    /// Wall clock age, for pools synced outside the registry
+    pub max_age: Option<Duration>,
+    /// Tick sets older than this are resynced with the state
+    pub max_tick_age_blocks: u64,
+    /// Pools whose last update moved the price by more than this fraction use
+    /// `volatile_max_age_blocks`
+    pub volatility_threshold: f64,
+    pub volatile_max_age_blocks: u64,
+    /// Checked from the first entry, put the highest `min_liquidity` first
+    pub tiers: Vec<LiquidityTier>,
+}
+
+impl Default for ResyncPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_blocks: 20,
+            max_age: None,
+            max_tick_age_blocks: 200,
+            volatility_threshold: 0.01,
+            volatile_max_age_blocks: 2,
+            tiers: Vec::new(),
+        }
+    }
+}
+
+impl ResyncPolicy {
+    /// Age limit in blocks for `pool` under the tier and volatility rules
+    pub fn max_age_for<P: Provider>(&self, pool: &AnyPool<P>) -> u64 {
+        let liquidity = pool.get_liquidity();
+        let mut max_age = self
+            .tiers
+            .iter()
+            .find(|t| liquidity >= t.min_liquidity)
+            .map_or(self.max_age_blocks, |t| t.max_age_blocks);
+
+        if pool.sync_status().volatility > self.volatility_threshold {
+            max_age = max_age.min(self.volatile_max_age_blocks);
+        }
+        max_age
+    }
+
+    /// The resync `pool` needs at `head`, `None` when it is fresh. A `bloom` of a
+    /// block the pool appears in makes it due right away.
+    pub fn action<P: Provider>(
+        &self,
+        key: &PoolRef,
+        pool: &AnyPool<P>,
+        head: u64,
+        bloom: Option<&Bloom>,
+    ) -> Option<SyncAction> {
+        let status = pool.sync_status();
+        let concentrated = !matches!(pool, AnyPool::V2(_));
+
+        let ticks_due = concentrated
+            && status
+                .ticks_age_blocks(head)
+                .is_none_or(|age| age > self.max_tick_age_blocks);
+        let state_due = status
+            .age_blocks(head)
+            .is_none_or(|age| age > self.max_age_for(pool))
+            || self
+                .max_age
+                .is_some_and(|max| status.age().is_none_or(|age| age > max))
+            || bloom.is_some_and(|b| in_bloom(b, key));
+
+        if ticks_due {
+            Some(SyncAction::StateAndTicks)
+        } else if state_due {
+            Some(SyncAction::State)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a block bloom may hold logs of `pool`, its contract for V2/V3 and its id
+/// topic for V4
+pub fn in_bloom(bloom: &Bloom, pool: &PoolRef) -> bool {
+    match pool {
+        PoolRef::Address(address) => {
+            bloom.contains_input(BloomInput::Raw(address.as_slice()))
+        }
+        PoolRef::V4(id) => bloom.contains_input(BloomInput::Raw(id.as_slice())),
+    }
+}
+
+impl<P: Provider> PoolRegistry<P> {
+    /// Pools the policy considers stale at `head`
+    pub fn due(
+        &self,
+        policy: &ResyncPolicy,
+        head: u64,
+        bloom: Option<&Bloom>,
+    ) -> Vec<(PoolRef, SyncAction)> {
+        let mut due: Vec<(PoolRef, SyncAction)> = self
+            .pools()
+            .filter_map(|(key, pool)| {
+                Some((*key, policy.action(key, pool, head, bloom)?))
+            })
+            .collect();
+        due.sort();
+        due
+    }
+
+    /// Resyncs every due pool at `head`, failures are returned and leave the pool due
+    pub async fn resync_due(
+        &mut self,
+        policy: &ResyncPolicy,
+        head: u64,
+        bloom: Option<&Bloom>,
+    ) -> Vec<PoolRef> {
+        let mut failed = Vec::new();
+        for (key, action) in self.due(policy, head, bloom) {
+            let ticks = action == SyncAction::StateAndTicks;
+            if self
+                .resync_pool(&key, ticks, Some(head))
+                .await
+                .is_err()
+            {
+                failed.push(key);
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{v2_base::V2Key, v2_pool::V2Pool};
+
+    #[test]
+    fn policy_rules() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let pair = Address::repeat_byte(7);
+        let key = V2Key {
+            fee: 3000,
+            address: pair,
+            token0: Address::repeat_byte(1),
+            token1: Address::repeat_byte(2),
+        };
+        let mut pool: AnyPool<_> =
+            V2Pool::new_from_key(key, Address::ZERO, provider).into();
+        let pool_ref = PoolRef::from(&pool);
+        let policy = ResyncPolicy::default();
+
+        assert_eq!(
+            policy.action(&pool_ref, &pool, 10, None),
+            Some(SyncAction::State)
+        );
+
+        pool.sync_status_mut().mark_synced(Some(10));
+        assert_eq!(policy.action(&pool_ref, &pool, 30, None), None);
+        assert_eq!(
+            policy.action(&pool_ref, &pool, 31, None),
+            Some(SyncAction::State)
+        );
+
+        let mut bloom = Bloom::default();
+        bloom.accrue(BloomInput::Raw(pair.as_slice()));
+        assert_eq!(
+            policy.action(&pool_ref, &pool, 11, Some(&bloom)),
+            Some(SyncAction::State)
+        );
+
+        pool.sync_status_mut().volatility = 0.05;
+        assert_eq!(
+            policy.action(&pool_ref, &pool, 13, None),
+            Some(SyncAction::State)
+        );
+    }
+}
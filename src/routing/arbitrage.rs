@@ -0,0 +1,282 @@
+use std::cmp::Reverse;
+
+use alloy::primitives::{Address, U256};
+use alloy_provider::Provider;
+
+use crate::{
+    any_pool::AnyPool,
+    pool::UniPool,
+    routing::graph::{Hop, TokenGraph},
+};
+
+const DEFAULT_MAX_CYCLE_LEN: usize = 3;
+const DEFAULT_SEARCH_ITERATIONS: usize = 48;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArbitrageConfig {
+    /// Longest token loop searched, in pools
+    pub max_cycle_len: usize,
+    /// Upper bound of the input searched for every cycle, in the start token
+    pub max_amount_in: U256,
+    /// Cycles whose best profit is not above this are dropped
+    pub min_profit: U256,
+    /// Golden-section iterations used to find the optimal input
+    pub search_iterations: usize,
+}
+
+impl ArbitrageConfig {
+    pub fn new(max_amount_in: U256) -> Self {
+        Self {
+            max_cycle_len: DEFAULT_MAX_CYCLE_LEN,
+            max_amount_in,
+            min_profit: U256::ZERO,
+            search_iterations: DEFAULT_SEARCH_ITERATIONS,
+        }
+    }
+}
+
+/// A token loop whose mid prices and fees multiply to more than one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    /// Tokens visited, the first one is repeated at the end
+    pub tokens: Vec<Address>,
+    pub hops: Vec<Hop>,
+    /// Sum of `-ln(rate * (1 - fee))` over the hops, negative for a candidate
+    pub log_weight: f64,
+}
+
+/// A cycle confirmed with the trade simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opportunity {
+    pub cycle: Cycle,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub profit: U256,
+}
+
+impl<P: Provider> TokenGraph<P> {
+    /// Finds every simple token loop of at most `max_len` pools whose mid price log
+    /// weights add up to a negative number. Each loop is reported once per direction,
+    /// starting at its smallest token address.
+    pub fn find_cycles(&self, max_len: usize) -> Vec<Cycle> {
+        let weights: Vec<Option<(f64, f64)>> =
+            self.pools.iter().map(edge_weights).collect();
+
+        let mut tokens: Vec<Address> = self.tokens().copied().collect();
+        tokens.sort();
+
+        let mut cycles = Vec::new();
+        for start in tokens {
+            let mut path = CycleSearch {
+                start,
+                max_len,
+                tokens: vec![start],
+                hops: Vec::new(),
+                weight: 0.0,
+            };
+            self.search_cycles(&weights, &mut path, &mut cycles);
+        }
+        cycles
+    }
+
+    fn search_cycles(
+        &self,
+        weights: &[Option<(f64, f64)>],
+        path: &mut CycleSearch,
+        cycles: &mut Vec<Cycle>,
+    ) {
+        if path.hops.len() >= path.max_len {
+            return;
+        }
+        let token = *path
+            .tokens
+            .last()
+            .expect("search starts with a token");
+
+        for pool in self.pools_of(&token) {
+            let Some(hop) = self.hop(*pool, &token) else {
+                continue;
+            };
+            let Some((w0, w1)) = weights[*pool] else {
+                continue;
+            };
+            let (next, hop_weight) = if hop.from0 {
+                (*self.pools[*pool].get_b(), w0)
+            } else {
+                (*self.pools[*pool].get_a(), w1)
+            };
+            let weight = path.weight + hop_weight;
+
+            if next == path.start {
+                // a single pool back and forth is never a cycle
+                if !path.hops.is_empty() && weight < 0.0 {
+                    let mut tokens = path.tokens.clone();
+                    tokens.push(next);
+                    let mut hops = path.hops.clone();
+                    hops.push(hop);
+                    cycles.push(Cycle {
+                        tokens,
+                        hops,
+                        log_weight: weight,
+                    });
+                }
+                continue;
+            }
+            // canonical rotation and simple paths only
+            if next < path.start || path.tokens.contains(&next) {
+                continue;
+            }
+
+            path.tokens.push(next);
+            path.hops.push(hop);
+            let prev_weight = path.weight;
+            path.weight = weight;
+
+            self.search_cycles(weights, path, cycles);
+
+            path.weight = prev_weight;
+            path.hops.pop();
+            path.tokens.pop();
+        }
+    }
+
+    /// Finds candidate cycles from mid prices, then confirms each one with `trade`
+    /// and searches the input that maximizes profit
+    pub fn scan_arbitrage(&mut self, config: &ArbitrageConfig) -> Vec<Opportunity> {
+        let mut opportunities = Vec::new();
+
+        for cycle in self.find_cycles(config.max_cycle_len) {
+            let (amount_in, profit) = self.optimal_input(&cycle.hops, config);
+            if profit <= config.min_profit || profit.is_zero() {
+                continue;
+            }
+            opportunities.push(Opportunity {
+                cycle,
+                amount_in,
+                amount_out: amount_in + profit,
+                profit,
+            });
+        }
+
+        opportunities.sort_by_key(|o| Reverse(o.profit));
+        opportunities
+    }
+
+    /// Golden-section search of the profit over `0..=max_amount_in`. Inputs that fail
+    /// to trade count as zero profit, so the search moves away from them.
+    pub fn optimal_input(
+        &mut self,
+        hops: &[Hop],
+        config: &ArbitrageConfig,
+    ) -> (U256, U256) {
+        // 0.618 and 0.382 scaled by 1e6
+        let phi = U256::from(618_034);
+        let scale = U256::from(1_000_000);
+
+        let mut lo = U256::ZERO;
+        let mut hi = config.max_amount_in;
+        let mut best = (U256::ZERO, U256::ZERO);
+
+        for _ in 0..config.search_iterations {
+            if hi <= lo + U256::from(2) {
+                break;
+            }
+            let span = hi - lo;
+            let m1 = hi - span * phi / scale;
+            let m2 = lo + span * phi / scale;
+
+            let p1 = self.cycle_profit(hops, m1);
+            let p2 = self.cycle_profit(hops, m2);
+            for (amount, profit) in [(m1, p1), (m2, p2)] {
+                if profit > best.1 {
+                    best = (amount, profit);
+                }
+            }
+
+            if p1 < p2 {
+                lo = m1;
+            } else {
+                hi = m2;
+            }
+        }
+
+        best
+    }
+
+    fn cycle_profit(&mut self, hops: &[Hop], amount_in: U256) -> U256 {
+        match self.quote_path(hops, amount_in) {
+            Ok(quote) => quote.amount_out().saturating_sub(amount_in),
+            Err(_) => U256::ZERO,
+        }
+    }
+}
+
+struct CycleSearch {
+    start: Address,
+    max_len: usize,
+    tokens: Vec<Address>,
+    hops: Vec<Hop>,
+    weight: f64,
+}
+
+/// `-ln(rate * (1 - fee))` for selling token0 and for selling token1 on the pool,
+/// from its mid price. `None` when the pool has no usable price.
+fn edge_weights<P: Provider>(pool: &AnyPool<P>) -> Option<(f64, f64)> {
+    let fee = match pool {
+        AnyPool::V2(v2_pool) => v2_pool.key.fee as f64 / 1e6,
+        AnyPool::V3(v3_pool) => f64::from(v3_pool.key.fee) / 1e6,
+        AnyPool::V4(v4_pool) => f64::from(v4_pool.key.fee) / 1e6,
+    };
+    let price = pool.get_price().to_f64();
+    if !price.is_finite() || price <= 0.0 || fee >= 1.0 {
+        return None;
+    }
+
+    let fee_weight = -(1.0 - fee).ln();
+    Some((-price.ln() + fee_weight, price.ln() + fee_weight))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::routing::tests::{
+        pair,
+        E18,
+    };
+
+    #[test]
+    fn mispriced_pools_form_a_cycle() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let mut graph = TokenGraph::new(vec![
+            pair(provider.clone(), 1, 2, 1000, 1000),
+            pair(provider, 1, 2, 1000, 1100),
+        ]);
+
+        let cycles = graph.find_cycles(DEFAULT_MAX_CYCLE_LEN);
+        assert_eq!(cycles.len(), 1);
+        // sell token 1 where it is dear, buy it back where it is cheap
+        assert_eq!(cycles[0].hops[0].pool, 1);
+        assert!(cycles[0].log_weight < 0.0);
+
+        let config = ArbitrageConfig::new(U256::from(200 * E18));
+        let opportunities = graph.scan_arbitrage(&config);
+        let [best] = &opportunities[..] else {
+            panic!("one opportunity");
+        };
+        assert!(!best.amount_in.is_zero());
+        assert!(!best.profit.is_zero());
+        assert_eq!(best.amount_out, best.amount_in + best.profit);
+
+        // the search lands on the top of the profit curve
+        let hops = best.cycle.hops.clone();
+        for amount in [
+            best.amount_in * U256::from(9) / U256::from(10),
+            best.amount_in * U256::from(11) / U256::from(10),
+        ] {
+            assert!(graph.cycle_profit(&hops, amount) <= best.profit);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+use alloy_provider::Provider;
+
+use crate::{
+    any_pool::AnyPool,
+    any_trade::Trade,
+    err::RouteError,
+    pool::UniPool,
+};
+
+/// One swap through a pool of the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    pub pool: usize,
+    pub from0: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathQuote {
+    pub hops: Vec<Hop>,
+    /// Tokens visited, `hops.len() + 1` entries starting with the input token
+    pub tokens: Vec<Address>,
+    /// Amount entering each hop followed by the final output
+    pub amounts: Vec<U256>,
+}
+
+impl PathQuote {
+    pub fn amount_in(&self) -> U256 {
+        self.amounts[0]
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        *self
+            .amounts
+            .last()
+            .expect("a quote holds at least the input amount")
+    }
+}
+
+/// Tokens as nodes and pools as edges. Every pool connects its `get_a` and `get_b`
+/// tokens in both directions.
+pub struct TokenGraph<P: Provider> {
+    pub pools: Vec<AnyPool<P>>,
+    edges: HashMap<Address, Vec<usize>>,
+}
+
+impl<P: Provider> TokenGraph<P> {
+    pub fn new(pools: Vec<AnyPool<P>>) -> Self {
+        let mut graph = Self {
+            pools: Vec::with_capacity(pools.len()),
+            edges: HashMap::new(),
+        };
+        for pool in pools {
+            graph.add_pool(pool);
+        }
+        graph
+    }
+
+    /// Adds a pool and returns its index
+    pub fn add_pool(&mut self, pool: AnyPool<P>) -> usize {
+        let idx = self.pools.len();
+        self.edges
+            .entry(*pool.get_a())
+            .or_default()
+            .push(idx);
+        self.edges
+            .entry(*pool.get_b())
+            .or_default()
+            .push(idx);
+        self.pools.push(pool);
+        idx
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Address> {
+        self.edges.keys()
+    }
+
+    /// Indices of the pools touching `token`
+    pub fn pools_of(&self, token: &Address) -> &[usize] {
+        self.edges
+            .get(token)
+            .map(|p| p.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Indices of the pools trading `token_a` against `token_b`
+    pub fn pools_between(&self, token_a: &Address, token_b: &Address) -> Vec<usize> {
+        self.pools_of(token_a)
+            .iter()
+            .copied()
+            .filter(|i| self.other_token(*i, token_a) == Some(*token_b))
+            .collect()
+    }
+
+    /// Builds the hop that sells `token_in` on pool `pool`
+    pub fn hop(&self, pool: usize, token_in: &Address) -> Option<Hop> {
+        let p = self.pools.get(pool)?;
+        if p.get_a() == token_in {
+            Some(Hop {
+                pool,
+                from0: true,
+            })
+        } else if p.get_b() == token_in {
+            Some(Hop {
+                pool,
+                from0: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn other_token(&self, pool: usize, token: &Address) -> Option<Address> {
+        let p = self.pools.get(pool)?;
+        if p.get_a() == token {
+            Some(*p.get_b())
+        } else if p.get_b() == token {
+            Some(*p.get_a())
+        } else {
+            None
+        }
+    }
+
+    fn hop_tokens(&self, hop: &Hop) -> Option<(Address, Address)> {
+        let p = self.pools.get(hop.pool)?;
+        if hop.from0 {
+            Some((*p.get_a(), *p.get_b()))
+        } else {
+            Some((*p.get_b(), *p.get_a()))
+        }
+    }
+
+    /// Chains `trade` hop by hop, feeding each output into the next hop
+    pub fn quote_path(
+        &mut self,
+        path: &[Hop],
+        amount_in: U256,
+    ) -> Result<PathQuote, RouteError> {
+        let first = path.first().ok_or(RouteError::Path)?;
+        let (token_in, _) = self.hop_tokens(first).ok_or(RouteError::Path)?;
+
+        let mut tokens = vec![token_in];
+        let mut amounts = vec![amount_in];
+        let mut amount = amount_in;
+
+        for hop in path {
+            let (hop_in, hop_out) = self.hop_tokens(hop).ok_or(RouteError::Path)?;
+            if Some(&hop_in) != tokens.last() {
+                return Err(RouteError::Path);
+            }
+
+            amount = self.pools[hop.pool]
+                .trade(amount, hop.from0)?
+                .amount_out();
+
+            tokens.push(hop_out);
+            amounts.push(amount);
+        }
+
+        Ok(PathQuote {
+            hops: path.to_vec(),
+            tokens,
+            amounts,
+        })
+    }
+
+    /// Searches every simple path of at most `max_hops` pools and returns the one with
+    /// the largest output. Paths where any hop fails to trade are dropped.
+    pub fn best_path(
+        &mut self,
+        token_in: Address,
+        token_out: Address,
+        amount: U256,
+        max_hops: usize,
+    ) -> Option<PathQuote> {
+        let mut best = None;
+        let mut search = PathSearch {
+            target: token_out,
+            max_hops,
+            hops: Vec::new(),
+            tokens: vec![token_in],
+            amounts: vec![amount],
+        };
+        self.search(&mut search, &mut best);
+        best
+    }
+
+    fn search(&mut self, search: &mut PathSearch, best: &mut Option<PathQuote>) {
+        if search.hops.len() >= search.max_hops {
+            return;
+        }
+        let token = *search
+            .tokens
+            .last()
+            .expect("search starts with a token");
+        let amount = *search
+            .amounts
+            .last()
+            .expect("search starts with an amount");
+
+        for pool in self.pools_of(&token).to_vec() {
+            let Some(next) = self.other_token(pool, &token) else {
+                continue;
+            };
+            // simple paths only, the target may close the path
+            if search.tokens.contains(&next) {
+                continue;
+            }
+            let Some(hop) = self.hop(pool, &token) else {
+                continue;
+            };
+            let Ok(trade) = self.pools[pool].trade(amount, hop.from0) else {
+                continue;
+            };
+            let out = trade.amount_out();
+            if out.is_zero() {
+                continue;
+            }
+
+            search.hops.push(hop);
+            search.tokens.push(next);
+            search.amounts.push(out);
+
+            if next == search.target {
+                if best
+                    .as_ref()
+                    .is_none_or(|b| out > b.amount_out())
+                {
+                    *best = Some(PathQuote {
+                        hops: search.hops.clone(),
+                        tokens: search.tokens.clone(),
+                        amounts: search.amounts.clone(),
+                    });
+                }
+            } else {
+                self.search(search, best);
+            }
+
+            search.hops.pop();
+            search.tokens.pop();
+            search.amounts.pop();
+        }
+    }
+}
+
+struct PathSearch {
+    target: Address,
+    max_hops: usize,
+    hops: Vec<Hop>,
+    tokens: Vec<Address>,
+    amounts: Vec<U256>,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::routing::tests::{
+        pair,
+        E18,
+    };
+
+    #[test]
+    fn two_hops_beat_a_bad_direct_pool() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let mut graph = TokenGraph::new(vec![
+            pair(provider.clone(), 1, 3, 1000, 900),
+            pair(provider.clone(), 1, 2, 1000, 1000),
+            pair(provider, 2, 3, 1000, 1000),
+        ]);
+
+        assert_eq!(graph.tokens().count(), 3);
+        assert_eq!(graph.pools_of(&b).len(), 2);
+        assert_eq!(graph.pools_between(&a, &c), vec![0]);
+        assert_eq!(
+            graph.hop(0, &c),
+            Some(Hop {
+                pool: 0,
+                from0: false,
+            })
+        );
+
+        let amount = U256::from(E18);
+        let best = graph.best_path(a, c, amount, 3).unwrap();
+        assert_eq!(best.tokens, vec![a, b, c]);
+        let direct = graph
+            .quote_path(&[graph.hop(0, &a).unwrap()], amount)
+            .unwrap();
+        assert!(best.amount_out() > direct.amount_out());
+        assert_eq!(graph.best_path(a, c, amount, 1).unwrap().hops, direct.hops);
+
+        // the second hop starts from a token the first one did not output
+        let broken = [
+            graph.hop(1, &a).unwrap(),
+            graph.hop(0, &a).unwrap(),
+        ];
+        assert!(matches!(
+            graph.quote_path(&broken, amount),
+            Err(RouteError::Path)
+        ));
+    }
+}
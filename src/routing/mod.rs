@@ -0,0 +1,46 @@
+pub mod arbitrage;
+pub mod graph;
+pub mod split;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::primitives::{
+        Address,
+        U256,
+    };
+    use alloy_provider::Provider;
+
+    use crate::{
+        any_pool::AnyPool,
+        v2_base::{
+            V2Key,
+            V2State,
+        },
+        v2_pool::V2Pool,
+    };
+
+    pub const E18: u128 = 10u128.pow(18);
+
+    /// A 0.3% V2 pair between `Address::repeat_byte(token0)` and
+    /// `Address::repeat_byte(token1)` holding the reserves in whole tokens
+    pub fn pair<P: Provider>(
+        provider: P,
+        token0: u8,
+        token1: u8,
+        reserves0: u128,
+        reserves1: u128,
+    ) -> AnyPool<P> {
+        let key = V2Key {
+            fee: 3000,
+            address: Address::repeat_byte(token0 * 16 + token1),
+            token0: Address::repeat_byte(token0),
+            token1: Address::repeat_byte(token1),
+        };
+        let mut pool = V2Pool::new_from_key(key, Address::ZERO, provider);
+        pool.state = V2State {
+            reserves0: U256::from(reserves0 * E18),
+            reserves1: U256::from(reserves1 * E18),
+        };
+        AnyPool::V2(pool)
+    }
+}
@@ -0,0 +1,151 @@
+use alloy::primitives::{Address, U256};
+use alloy_provider::Provider;
+
+use crate::{any_trade::Trade, pool::UniPool, routing::graph::TokenGraph};
+
+/// Default number of chunks an order is cut into
+pub const DEFAULT_PARTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub pool: usize,
+    pub from0: bool,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitQuote {
+    /// Pools that received part of the order, in graph index order
+    pub allocations: Vec<Allocation>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Input no pool could absorb, e.g. when every pool ran out of synced ticks
+    pub unfilled: U256,
+}
+
+impl<P: Provider> TokenGraph<P> {
+    /// Splits `amount_in` across every pool trading `token_in` for `token_out`.
+    ///
+    /// The order is cut into `parts` equal chunks and each chunk goes to the pool
+    /// whose output grows the most when its allocation is increased by that chunk.
+    /// Because outputs are concave in the input this converges to the allocation
+    /// where the marginal prices of all used pools are equal, within one chunk.
+    pub fn split_order(
+        &mut self,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        parts: usize,
+    ) -> Option<SplitQuote> {
+        let candidates: Vec<_> = self
+            .pools_between(&token_in, &token_out)
+            .into_iter()
+            .filter_map(|pool| self.hop(pool, &token_in))
+            .collect();
+        if candidates.is_empty() || amount_in.is_zero() {
+            return None;
+        }
+
+        let parts = U256::from(parts.max(1));
+        let chunk = (amount_in / parts).max(U256::ONE);
+
+        let mut allocations: Vec<Allocation> = candidates
+            .iter()
+            .map(|hop| Allocation {
+                pool: hop.pool,
+                from0: hop.from0,
+                amount_in: U256::ZERO,
+                amount_out: U256::ZERO,
+            })
+            .collect();
+
+        let mut remaining = amount_in;
+        while !remaining.is_zero() {
+            // the last chunk takes whatever the division left over
+            let step = if remaining < chunk * U256::from(2) {
+                remaining
+            } else {
+                chunk
+            };
+
+            // (allocation index, output with the extra chunk, marginal output)
+            let mut best: Option<(usize, U256, U256)> = None;
+            for (i, alloc) in allocations.iter().enumerate() {
+                let Ok(trade) =
+                    self.pools[alloc.pool].trade(alloc.amount_in + step, alloc.from0)
+                else {
+                    continue;
+                };
+                let out = trade.amount_out();
+                let gain = out.saturating_sub(alloc.amount_out);
+                if best.is_none_or(|(_, _, best_gain)| gain > best_gain) {
+                    best = Some((i, out, gain));
+                }
+            }
+
+            let Some((i, out, _)) = best else {
+                break;
+            };
+            allocations[i].amount_in += step;
+            allocations[i].amount_out = out;
+            remaining -= step;
+        }
+
+        let allocations: Vec<Allocation> = allocations
+            .into_iter()
+            .filter(|a| !a.amount_in.is_zero())
+            .collect();
+        if allocations.is_empty() {
+            return None;
+        }
+
+        let amount_out = allocations
+            .iter()
+            .fold(U256::ZERO, |acc, a| acc + a.amount_out);
+
+        Some(SplitQuote {
+            allocations,
+            amount_in: amount_in - remaining,
+            amount_out,
+            unfilled: remaining,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::routing::tests::{
+        pair,
+        E18,
+    };
+
+    #[test]
+    fn split_beats_single_pools() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut graph = TokenGraph::new(vec![
+            pair(provider.clone(), 1, 2, 1000, 1000),
+            pair(provider, 1, 2, 600, 600),
+        ]);
+
+        let amount = U256::from(100 * E18);
+        let split = graph
+            .split_order(a, b, amount, DEFAULT_PARTS)
+            .unwrap();
+        assert_eq!(split.allocations.len(), 2);
+        assert_eq!(split.amount_in, amount);
+        assert!(split.unfilled.is_zero());
+
+        for pool in 0..2 {
+            let single = graph.pools[pool].trade(amount, true).unwrap();
+            assert!(split.amount_out > single.amount_out());
+        }
+        // the deeper pool takes the larger share
+        assert!(split.allocations[0].amount_in > split.allocations[1].amount_in);
+    }
+}
@@ -0,0 +1,256 @@
+use alloy::primitives::{
+    address,
+    aliases::U160,
+    hex,
+    uint,
+    Address,
+    Bytes,
+    I256,
+    U256,
+};
+use alloy_sol_types::{
+    SolCall,
+    SolValue,
+};
+
+use crate::{
+    config::ProtocolKind,
+    err::SimulationError,
+    simulation::{
+        StateSnapshot,
+        SwapCall,
+    },
+    sol_types::{
+        IPoolManager::{
+            swapCall,
+            unlockCall,
+            SwapParams,
+        },
+        IUniswapV2Pair,
+        V3Pool,
+    },
+};
+
+/// Account the swaps are sent from, it holds `SWAPPER_CODE`
+pub const SWAPPER: Address = address!("5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a");
+
+/// Callback contract of `SWAPPER`. `unlockCallback(bytes)` forwards its argument to
+/// the caller and reverts with what the call returned, any other call reverts with
+/// its two first words, the deltas of `uniswapV3SwapCallback`. The swap reverts
+/// once its output is known, so the input is never paid. A V3 pool still reads its
+/// input token balance before the callback, the snapshot needs the token code and
+/// the pool's balance slot for that, see `StateRecorder::record_v3_pool`.
+pub const SWAPPER_CODE: [u8; 58] = hex!(
+    "60003560e01c6391dd734614601b576040600460003760406000fd"
+    "5b602435806044600037600060008260006000335af13d6000803e3d6000fd"
+);
+
+/// `sqrtPriceX96` limits one step inside the tick math range
+pub const MIN_SQRT_PRICE_LIMIT: U160 = uint!(4295128740_U160);
+pub const MAX_SQRT_PRICE_LIMIT: U160 =
+    uint!(1461446703485210103287273052203988822378723970341_U160);
+
+/// Calldata `SWAPPER` sends to `call.pool`: the V3 pool `swap`, or a PoolManager
+/// `unlock` wrapping the V4 `swap`. V2 pairs are asked for an output instead, see
+/// `pair_swap_calldata`. Other pool kinds are `Unsupported`.
+pub fn swap_calldata(call: &SwapCall) -> Result<Bytes, SimulationError> {
+    let amount =
+        I256::try_from(call.amount_in).map_err(|_| SimulationError::Unsupported)?;
+    let limit = if call.from0 {
+        MIN_SQRT_PRICE_LIMIT
+    } else {
+        MAX_SQRT_PRICE_LIMIT
+    };
+
+    let data = match call.kind {
+        ProtocolKind::UniswapV3 => V3Pool::swapCall {
+            recipient: SWAPPER,
+            zeroForOne: call.from0,
+            amountSpecified: amount,
+            sqrtPriceLimitX96: limit,
+            data: Bytes::new(),
+        }
+        .abi_encode(),
+        ProtocolKind::UniswapV4 => {
+            let key = call.v4_key.ok_or(SimulationError::MissingState)?;
+            // V4 specifies exact inputs as negative amounts
+            let swap = swapCall {
+                key: key.into(),
+                params: SwapParams {
+                    zeroForOne: call.from0,
+                    amountSpecified: -amount,
+                    sqrtPriceLimitX96: limit,
+                },
+                hookData: Bytes::new(),
+            };
+            unlockCall {
+                data: Bytes::from(swap.abi_encode()),
+            }
+            .abi_encode()
+        }
+        _ => return Err(SimulationError::Unsupported),
+    };
+    Ok(Bytes::from(data))
+}
+
+/// V2 pair `swap` paying `amount_out` to `SWAPPER`. The pair only checks that its
+/// balances still cover the fee adjusted reserves product, so the output of a real
+/// swap is the largest `amount_out` that succeeds once the input was credited.
+pub fn pair_swap_calldata(call: &SwapCall, amount_out: U256) -> Bytes {
+    let (amount0_out, amount1_out) = if call.from0 {
+        (U256::ZERO, amount_out)
+    } else {
+        (amount_out, U256::ZERO)
+    };
+    let swap = IUniswapV2Pair::swapCall {
+        amount0Out: amount0_out,
+        amount1Out: amount1_out,
+        to: SWAPPER,
+        data: Bytes::new(),
+    };
+    Bytes::from(swap.abi_encode())
+}
+
+/// Storage write standing in for the input transfer a router makes before a V2 `swap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputCredit {
+    pub token: Address,
+    pub slot: U256,
+    /// Pair balance of `token` including the input
+    pub balance: U256,
+}
+
+/// Credits `call.amount_in` to the pair's balance slot of the input token. Needs the
+/// balances `StateRecorder::record_v2_pair` records.
+pub fn v2_input_credit(
+    snapshot: &StateSnapshot,
+    call: &SwapCall,
+) -> Result<InputCredit, SimulationError> {
+    let [token0, token1] = snapshot.tokens_of(&call.pool)[..] else {
+        return Err(SimulationError::MissingState);
+    };
+    let token = if call.from0 {
+        token0
+    } else {
+        token1
+    };
+    let slot = snapshot
+        .balance_slot(&token, &call.pool)
+        .ok_or(SimulationError::MissingState)?;
+    let balance = snapshot
+        .storage(&token, slot)
+        .unwrap_or_default()
+        .checked_add(call.amount_in)
+        .ok_or(SimulationError::Unsupported)?;
+
+    Ok(InputCredit {
+        token,
+        slot,
+        balance,
+    })
+}
+
+/// Output amount in the revert data of a `swap_calldata` call. Revert data of any
+/// other shape is the pool's own error.
+pub fn decode_output(call: &SwapCall, revert: &[u8]) -> Result<U256, SimulationError> {
+    let out = match call.kind {
+        // pool deltas, the output is negative
+        ProtocolKind::UniswapV3 if revert.len() == 64 => {
+            let (amount0, amount1) = <(I256, I256)>::abi_decode_params(revert)
+                .map_err(|_| revert_error(revert))?;
+            let out = if call.from0 {
+                amount1
+            } else {
+                amount0
+            };
+            (out <= I256::ZERO).then(|| out.unsigned_abs())
+        }
+        // caller deltas packed as two int128, the output is positive
+        ProtocolKind::UniswapV4 if revert.len() == 32 => {
+            let half = if call.from0 {
+                &revert[16..]
+            } else {
+                &revert[..16]
+            };
+            let out = i128::from_be_bytes(half.try_into().expect("16 bytes"));
+            (out >= 0).then(|| U256::from(out))
+        }
+        _ => None,
+    };
+    out.ok_or_else(|| revert_error(revert))
+}
+
+fn revert_error(revert: &[u8]) -> SimulationError {
+    SimulationError::Execution(format!("swap reverted with 0x{}", hex::encode(revert)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        any_pool::V4Key,
+        sol_types::IUnlockCallback::unlockCallbackCall,
+    };
+
+    #[test]
+    fn swapper_calls_and_outputs() {
+        // the branch compares against the real callback selector
+        assert_eq!(SWAPPER_CODE[7..11], unlockCallbackCall::SELECTOR);
+        // JUMPDEST where the PUSH1 before JUMPI points
+        assert_eq!(SWAPPER_CODE[0x0c..0x0f], [0x60, 0x1b, 0x57]);
+        assert_eq!(SWAPPER_CODE[0x1b], 0x5b);
+
+        let mut call = SwapCall {
+            kind: ProtocolKind::UniswapV3,
+            pool: Address::repeat_byte(1),
+            v4_key: None,
+            from0: true,
+            amount_in: U256::from(1_000),
+        };
+        let data = swap_calldata(&call).unwrap();
+        let swap = V3Pool::swapCall::abi_decode(&data).unwrap();
+        assert_eq!(swap.amountSpecified, I256::try_from(1_000).unwrap());
+        assert_eq!(swap.sqrtPriceLimitX96, MIN_SQRT_PRICE_LIMIT);
+
+        let deltas = (
+            I256::try_from(1_000).unwrap(),
+            I256::try_from(-990).unwrap(),
+        )
+            .abi_encode_params();
+        assert_eq!(decode_output(&call, &deltas).unwrap(), U256::from(990));
+        assert!(decode_output(&call, &deltas[..32]).is_err());
+
+        call.kind = ProtocolKind::UniswapV4;
+        assert!(matches!(
+            swap_calldata(&call),
+            Err(SimulationError::MissingState)
+        ));
+        call.v4_key = Some(V4Key::default());
+        call.from0 = false;
+        let data = swap_calldata(&call).unwrap();
+        let unlock = unlockCall::abi_decode(&data).unwrap();
+        let swap = swapCall::abi_decode(&unlock.data).unwrap();
+        assert_eq!(swap.params.amountSpecified, I256::try_from(-1_000).unwrap());
+        assert_eq!(swap.params.sqrtPriceLimitX96, MAX_SQRT_PRICE_LIMIT);
+
+        // amount0 received, amount1 paid
+        let mut delta = [0u8; 32];
+        delta[..16].copy_from_slice(&990i128.to_be_bytes());
+        delta[16..].copy_from_slice(&(-1_000i128).to_be_bytes());
+        assert_eq!(decode_output(&call, &delta).unwrap(), U256::from(990));
+
+        // pairs are asked for the output, token0 here
+        call.kind = ProtocolKind::UniswapV2;
+        assert!(matches!(
+            swap_calldata(&call),
+            Err(SimulationError::Unsupported)
+        ));
+        let data = pair_swap_calldata(&call, U256::from(990));
+        let swap = IUniswapV2Pair::swapCall::abi_decode(&data).unwrap();
+        assert_eq!(
+            (swap.amount0Out, swap.amount1Out),
+            (U256::from(990), U256::ZERO)
+        );
+        assert_eq!(swap.to, SWAPPER);
+    }
+}
@@ -0,0 +1,809 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io,
+    path::Path,
+};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{
+        aliases::{
+            I24,
+            U24,
+        },
+        keccak256,
+        Address,
+        Bytes,
+        B256,
+        I256,
+        U256,
+    },
+};
+use alloy_provider::Provider;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    any_pool::V4Key,
+    config::ProtocolKind,
+    err::{
+        SimulationError,
+        TradeError,
+    },
+    pool_address::v4_pool_id,
+    sol_types::{
+        IUniswapV2Pair::IUniswapV2PairInstance,
+        IERC20::IERC20Instance,
+    },
+    v2_base::V2State,
+    v3_base::{
+        bitmap_math::extract_ticks_from_bitmap,
+        ticks::Tick,
+        trade_math,
+        v3_state::V3State,
+    },
+};
+
+/// Uniswap V2 pair slot packing `reserve0 | reserve1 | blockTimestampLast`
+pub const V2_RESERVES_SLOT: u64 = 8;
+/// Uniswap V3 pool slots, forks that add fields to `slot0` use other layouts
+pub const V3_SLOT0_SLOT: u64 = 0;
+pub const V3_LIQUIDITY_SLOT: u64 = 4;
+pub const V3_TICKS_SLOT: u64 = 5;
+pub const V3_BITMAP_SLOT: u64 = 6;
+/// PoolManager slot of the `_pools` mapping and the offsets of a pool's fields
+pub const V4_POOLS_SLOT: u64 = 6;
+pub const V4_LIQUIDITY_OFFSET: u64 = 3;
+pub const V4_TICKS_OFFSET: u64 = 4;
+pub const V4_BITMAP_OFFSET: u64 = 5;
+
+pub mod evm;
+#[cfg(feature = "revm")]
+pub mod revm_executor;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub code: Bytes,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// A swap request against a pool of the snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapCall {
+    pub kind: ProtocolKind,
+    /// Pair or pool contract, the PoolManager for V4 pools
+    pub pool: Address,
+    /// Key of a V4 pool
+    #[serde(default)]
+    pub v4_key: Option<V4Key>,
+    pub from0: bool,
+    pub amount_in: U256,
+}
+
+/// Output of a real swap at the snapshot block, e.g. from QuoterV2 or `getAmountsOut`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedSwap {
+    pub call: SwapCall,
+    pub amount_out: U256,
+}
+
+/// Storage slot of `token` holding the balance of `holder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceSlot {
+    pub token: Address,
+    pub holder: Address,
+    pub slot: U256,
+}
+
+/// Code and storage of the accounts a swap touches at one block, enough to rebuild
+/// pool state or seed an EVM database without a node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub block: u64,
+    pub accounts: BTreeMap<Address, AccountSnapshot>,
+    #[serde(default)]
+    pub swaps: Vec<RecordedSwap>,
+    /// Token balances of the recorded pools, see `StateRecorder::record_token_balance`
+    #[serde(default)]
+    pub balance_slots: Vec<BalanceSlot>,
+}
+
+impl StateSnapshot {
+    pub fn new(block: u64) -> Self {
+        Self {
+            block,
+            ..Default::default()
+        }
+    }
+
+    pub fn storage(&self, account: &Address, slot: U256) -> Option<U256> {
+        self.accounts
+            .get(account)?
+            .storage
+            .get(&slot)
+            .copied()
+    }
+
+    pub fn balance_slot(&self, token: &Address, holder: &Address) -> Option<U256> {
+        self.balance_slots
+            .iter()
+            .find(|b| b.token == *token && b.holder == *holder)
+            .map(|b| b.slot)
+    }
+
+    /// Tokens with a recorded balance of `holder`, sorted like a pair's tokens
+    pub fn tokens_of(&self, holder: &Address) -> Vec<Address> {
+        let mut tokens: Vec<Address> = self
+            .balance_slots
+            .iter()
+            .filter(|b| b.holder == *holder)
+            .map(|b| b.token)
+            .collect();
+        tokens.sort();
+        tokens.dedup();
+        tokens
+    }
+
+    /// Returns `None` when the file does not exist or can't be parsed
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Rebuilds a V2 pair state from the reserves slot
+    pub fn v2_state(&self, pair: &Address) -> Option<V2State> {
+        let word = self.storage(pair, U256::from(V2_RESERVES_SLOT))?;
+        let mask = (U256::ONE << 112) - U256::ONE;
+
+        Some(V2State {
+            reserves0: word & mask,
+            reserves1: (word >> 112) & mask,
+        })
+    }
+
+    /// Rebuilds a V3 pool state from `slot0`, `liquidity`, the recorded bitmap words
+    /// and the ticks they mark
+    pub fn v3_state(
+        &self,
+        pool: &Address,
+        tick_spacing: I24,
+        words: &[i16],
+    ) -> Option<V3State> {
+        self.concentrated_state(pool, ConcentratedLayout::V3, tick_spacing, words)
+    }
+
+    /// Rebuilds a V4 pool state from the PoolManager storage of the pool
+    pub fn v4_state(
+        &self,
+        manager: &Address,
+        key: &V4Key,
+        words: &[i16],
+    ) -> Option<V3State> {
+        self.concentrated_state(
+            manager,
+            ConcentratedLayout::V4(v4_pool_id(key)),
+            key.tickspacing,
+            words,
+        )
+    }
+
+    fn concentrated_state(
+        &self,
+        account: &Address,
+        layout: ConcentratedLayout,
+        tick_spacing: I24,
+        words: &[i16],
+    ) -> Option<V3State> {
+        let slot0 = self.storage(account, layout.slot0())?;
+        let liquidity = self.storage(account, layout.liquidity())?;
+
+        let mut state = V3State::default(tick_spacing);
+        state.x96price = slot0 & ((U256::ONE << 160) - U256::ONE);
+        state.tick = decode_int24(slot0 >> 160);
+        state.liquidity = liquidity & ((U256::ONE << 128) - U256::ONE);
+
+        let mut ticks = Vec::new();
+        for pos in words {
+            let word = self.storage(account, layout.bitmap(*pos))?;
+            for tick in extract_ticks_from_bitmap(word, *pos, tick_spacing) {
+                let info = self.storage(account, layout.tick(tick))?;
+                // liquidityGross in the low half, liquidityNet in the high half
+                let net = U256::to::<u128>(&(info >> 128)) as i128;
+                ticks.push(Tick {
+                    tick,
+                    liquidity_net: Some(net),
+                });
+            }
+        }
+        state.ticks.insert_ticks(ticks);
+
+        Some(state)
+    }
+}
+
+/// Storage slot of `ticks[tick]`
+pub fn v3_tick_slot(tick: I24) -> U256 {
+    mapping_slot(I256::from(tick), U256::from(V3_TICKS_SLOT))
+}
+
+/// Storage slot of `tickBitmap[pos]`
+pub fn v3_bitmap_slot(pos: i16) -> U256 {
+    mapping_slot(
+        I256::try_from(pos).expect("i16 fits"),
+        U256::from(V3_BITMAP_SLOT),
+    )
+}
+
+/// PoolManager slot of `_pools[id]`, which holds `slot0`, as `StateLibrary` computes it
+pub fn v4_state_slot(id: B256) -> U256 {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(id.as_slice());
+    data[32..].copy_from_slice(&U256::from(V4_POOLS_SLOT).to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(data).0)
+}
+
+/// PoolManager slot of `_pools[id].ticks[tick]`
+pub fn v4_tick_slot(id: B256, tick: I24) -> U256 {
+    mapping_slot(
+        I256::from(tick),
+        v4_state_slot(id) + U256::from(V4_TICKS_OFFSET),
+    )
+}
+
+/// PoolManager slot of `_pools[id].tickBitmap[pos]`
+pub fn v4_bitmap_slot(id: B256, pos: i16) -> U256 {
+    mapping_slot(
+        I256::try_from(pos).expect("i16 fits"),
+        v4_state_slot(id) + U256::from(V4_BITMAP_OFFSET),
+    )
+}
+
+fn mapping_slot(key: I256, slot: U256) -> U256 {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    data[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(data).0)
+}
+
+/// Where a concentrated liquidity pool keeps its state: its own V3 contract or the
+/// V4 PoolManager under the pool id
+#[derive(Debug, Clone, Copy)]
+enum ConcentratedLayout {
+    V3,
+    V4(B256),
+}
+
+impl ConcentratedLayout {
+    fn slot0(&self) -> U256 {
+        match self {
+            Self::V3 => U256::from(V3_SLOT0_SLOT),
+            Self::V4(id) => v4_state_slot(*id),
+        }
+    }
+
+    fn liquidity(&self) -> U256 {
+        match self {
+            Self::V3 => U256::from(V3_LIQUIDITY_SLOT),
+            Self::V4(id) => v4_state_slot(*id) + U256::from(V4_LIQUIDITY_OFFSET),
+        }
+    }
+
+    fn tick(&self, tick: I24) -> U256 {
+        match self {
+            Self::V3 => v3_tick_slot(tick),
+            Self::V4(id) => v4_tick_slot(*id, tick),
+        }
+    }
+
+    fn bitmap(&self, pos: i16) -> U256 {
+        match self {
+            Self::V3 => v3_bitmap_slot(pos),
+            Self::V4(id) => v4_bitmap_slot(*id, pos),
+        }
+    }
+}
+
+fn decode_int24(word: U256) -> I24 {
+    let raw = (word & U256::from(0xff_ffff)).to::<u32>();
+    // sign extend from 24 bits
+    I24::try_from(((raw << 8) as i32) >> 8).expect("24 bit value")
+}
+
+/// Fetches code and storage into a snapshot, pinned to the snapshot block
+pub struct StateRecorder<P: Provider> {
+    pub provider: P,
+    pub snapshot: StateSnapshot,
+}
+
+impl<P: Provider> StateRecorder<P> {
+    pub fn new(provider: P, block: u64) -> Self {
+        Self {
+            provider,
+            snapshot: StateSnapshot::new(block),
+        }
+    }
+
+    fn block(&self) -> BlockId {
+        BlockId::number(self.snapshot.block)
+    }
+
+    /// Records the account code once and every slot in `slots`
+    pub async fn record_slots(
+        &mut self,
+        account: Address,
+        slots: &[U256],
+    ) -> Result<(), SimulationError> {
+        if !self.snapshot.accounts.contains_key(&account) {
+            let code = self
+                .provider
+                .get_code_at(account)
+                .block_id(self.block())
+                .await?;
+            self.snapshot.accounts.insert(
+                account,
+                AccountSnapshot {
+                    code,
+                    storage: BTreeMap::new(),
+                },
+            );
+        }
+
+        for slot in slots {
+            let value = self
+                .provider
+                .get_storage_at(account, *slot)
+                .block_id(self.block())
+                .await?;
+            self.snapshot
+                .accounts
+                .entry(account)
+                .or_default()
+                .storage
+                .insert(*slot, value);
+        }
+
+        Ok(())
+    }
+
+    /// Records the code and every slot `token.balanceOf(holder)` reads, as the node's
+    /// access list reports them. The slot holding the balance itself is kept in
+    /// `balance_slots`, so an EVM can credit the holder.
+    pub async fn record_token_balance(
+        &mut self,
+        token: Address,
+        holder: Address,
+    ) -> Result<(), SimulationError> {
+        let contract = IERC20Instance::new(token, &self.provider);
+        let call = contract.balanceOf(holder).block(self.block());
+        let balance = call.call().await?;
+        let request = call.into_transaction_request();
+        let access = self
+            .provider
+            .create_access_list(&request)
+            .block_id(self.block())
+            .await?;
+
+        let mut balance_slot = None;
+        for item in access.access_list.0 {
+            let slots: Vec<U256> = item
+                .storage_keys
+                .iter()
+                .map(|key| U256::from_be_bytes(key.0))
+                .collect();
+            self.record_slots(item.address, &slots).await?;
+
+            // proxies also read their implementation slot, the balance is the slot
+            // of the token itself that holds the returned value
+            if item.address == token && balance_slot.is_none() {
+                balance_slot = slots
+                    .into_iter()
+                    .find(|slot| self.snapshot.storage(&token, *slot) == Some(balance));
+            }
+        }
+
+        let slot = balance_slot.ok_or(SimulationError::MissingState)?;
+        self.snapshot.balance_slots.push(BalanceSlot {
+            token,
+            holder,
+            slot,
+        });
+        Ok(())
+    }
+
+    /// Records the balances of both pool tokens, `token0`/`token1` share their
+    /// selectors between V2 pairs and V3 pools
+    async fn record_pool_tokens(&mut self, pool: Address) -> Result<(), SimulationError> {
+        let contract = IUniswapV2PairInstance::new(pool, &self.provider);
+        let token0 = contract
+            .token0()
+            .block(self.block())
+            .call()
+            .await?;
+        let token1 = contract
+            .token1()
+            .block(self.block())
+            .call()
+            .await?;
+
+        self.record_token_balance(token0, pool).await?;
+        self.record_token_balance(token1, pool).await
+    }
+
+    /// Records the reserves and both token balances of the pair, the pair checks its
+    /// balances after paying out
+    pub async fn record_v2_pair(&mut self, pair: Address) -> Result<(), SimulationError> {
+        self.record_slots(pair, &[U256::from(V2_RESERVES_SLOT)])
+            .await?;
+        self.record_pool_tokens(pair).await
+    }
+
+    /// Records `slot0`, `liquidity`, the bitmap words and every tick they mark, plus
+    /// both token balances the pool reads before its swap callback
+    pub async fn record_v3_pool(
+        &mut self,
+        pool: Address,
+        tick_spacing: I24,
+        words: &[i16],
+    ) -> Result<(), SimulationError> {
+        self.record_concentrated(pool, ConcentratedLayout::V3, tick_spacing, words)
+            .await?;
+        self.record_pool_tokens(pool).await
+    }
+
+    /// Records the same fields of a V4 pool from the PoolManager storage. Hooks the
+    /// key names have to be recorded separately for an EVM to run them.
+    pub async fn record_v4_pool(
+        &mut self,
+        manager: Address,
+        key: &V4Key,
+        words: &[i16],
+    ) -> Result<(), SimulationError> {
+        self.record_concentrated(
+            manager,
+            ConcentratedLayout::V4(v4_pool_id(key)),
+            key.tickspacing,
+            words,
+        )
+        .await
+    }
+
+    async fn record_concentrated(
+        &mut self,
+        account: Address,
+        layout: ConcentratedLayout,
+        tick_spacing: I24,
+        words: &[i16],
+    ) -> Result<(), SimulationError> {
+        let mut slots = vec![
+            layout.slot0(),
+            layout.liquidity(),
+        ];
+        slots.extend(words.iter().map(|pos| layout.bitmap(*pos)));
+        self.record_slots(account, &slots).await?;
+
+        let mut tick_slots = Vec::new();
+        for pos in words {
+            let word = self
+                .snapshot
+                .storage(&account, layout.bitmap(*pos))
+                .unwrap_or_default();
+            for tick in extract_ticks_from_bitmap(word, *pos, tick_spacing) {
+                tick_slots.push(layout.tick(tick));
+            }
+        }
+        self.record_slots(account, &tick_slots).await
+    }
+
+    /// Stores the output of a real swap to compare against later
+    pub fn record_swap(&mut self, call: SwapCall, amount_out: U256) {
+        self.snapshot.swaps.push(RecordedSwap {
+            call,
+            amount_out,
+        });
+    }
+}
+
+/// Runs a pool's real `swap` against snapshot state. An EVM backend loads the
+/// snapshot code and storage into its database and calls the pool, see
+/// `revm_executor::RevmExecutor` behind the `revm` feature.
+pub trait SwapExecutor {
+    fn execute(
+        &mut self,
+        snapshot: &StateSnapshot,
+        call: &SwapCall,
+    ) -> Result<U256, SimulationError>;
+}
+
+/// Replays the swaps recorded in the snapshot
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordedExecutor;
+
+impl SwapExecutor for RecordedExecutor {
+    fn execute(
+        &mut self,
+        snapshot: &StateSnapshot,
+        call: &SwapCall,
+    ) -> Result<U256, SimulationError> {
+        snapshot
+            .swaps
+            .iter()
+            .find(|s| s.call == *call)
+            .map(|s| s.amount_out)
+            .ok_or(SimulationError::Unsupported)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub call: SwapCall,
+    /// Output of the crate's own math
+    pub local: U256,
+    /// Output of the executor
+    pub reference: U256,
+}
+
+impl Comparison {
+    pub fn diff(&self) -> U256 {
+        self.local.abs_diff(self.reference)
+    }
+
+    /// Whether the outputs differ by at most `tolerance_bps` of the reference
+    pub fn matches(&self, tolerance_bps: u32) -> bool {
+        self.diff() * U256::from(10_000) <= self.reference * U256::from(tolerance_bps)
+    }
+}
+
+/// Compares `V2State::trade` on the snapshot reserves with the executor
+pub fn compare_v2<E: SwapExecutor>(
+    snapshot: &StateSnapshot,
+    executor: &mut E,
+    call: &SwapCall,
+    fee: u32,
+) -> Result<Comparison, SimulationError> {
+    let state = snapshot
+        .v2_state(&call.pool)
+        .ok_or(SimulationError::MissingState)?;
+    let local = state
+        .trade(call.amount_in, fee, call.from0)
+        .ok_or(TradeError::V2)?;
+
+    Ok(Comparison {
+        call: *call,
+        local: local.amount_out,
+        reference: executor.execute(snapshot, call)?,
+    })
+}
+
+/// Compares `trade_math::trade` on the snapshot pool state with the executor
+pub fn compare_v3<E: SwapExecutor>(
+    snapshot: &StateSnapshot,
+    executor: &mut E,
+    call: &SwapCall,
+    fee: U24,
+    tick_spacing: I24,
+    words: &[i16],
+) -> Result<Comparison, SimulationError> {
+    let state = snapshot
+        .v3_state(&call.pool, tick_spacing, words)
+        .ok_or(SimulationError::MissingState)?;
+    let local = trade_math::trade(&state, &fee, call.amount_in, call.from0)?;
+
+    Ok(Comparison {
+        call: *call,
+        local: local.amount_out,
+        reference: executor.execute(snapshot, call)?,
+    })
+}
+
+/// Compares `trade_math::trade` on the snapshot V4 pool state with the executor,
+/// `fee` is the LP fee the pool charges
+pub fn compare_v4<E: SwapExecutor>(
+    snapshot: &StateSnapshot,
+    executor: &mut E,
+    call: &SwapCall,
+    fee: U24,
+    words: &[i16],
+) -> Result<Comparison, SimulationError> {
+    let key = call.v4_key.ok_or(SimulationError::MissingState)?;
+    let state = snapshot
+        .v4_state(&call.pool, &key, words)
+        .ok_or(SimulationError::MissingState)?;
+    let local = trade_math::trade(&state, &fee, call.amount_in, call.from0)?;
+
+    Ok(Comparison {
+        call: *call,
+        local: local.amount_out,
+        reference: executor.execute(snapshot, call)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2930::{
+            AccessList,
+            AccessListItem,
+        },
+        rpc::types::AccessListResult,
+    };
+
+    use super::*;
+    use crate::{
+        multicall::tests::{
+            mocked,
+            push_call,
+        },
+        sol_types::{
+            IUniswapV2Pair::{
+                token0Call,
+                token1Call,
+            },
+            IERC20::balanceOfCall,
+        },
+    };
+
+    #[test]
+    fn slot0_tick_sign() {
+        let tick = U256::from(0xff_fff6u64) | (U256::from(7) << 24);
+        assert_eq!(decode_int24(tick), I24::try_from(-10).unwrap());
+
+        let mut snapshot = StateSnapshot::new(1);
+        let pair = Address::repeat_byte(1);
+        let word = U256::from(5) | (U256::from(9) << 112) | (U256::from(3) << 224);
+        snapshot
+            .accounts
+            .entry(pair)
+            .or_default()
+            .storage
+            .insert(U256::from(V2_RESERVES_SLOT), word);
+
+        let state = snapshot.v2_state(&pair).unwrap();
+        assert_eq!(state.reserves0, U256::from(5));
+        assert_eq!(state.reserves1, U256::from(9));
+    }
+
+    #[test]
+    fn v4_state_from_manager_storage() {
+        let manager = Address::repeat_byte(4);
+        let key = V4Key {
+            currency1: Address::repeat_byte(1),
+            fee: U24::from(3000),
+            tickspacing: I24::try_from(60).unwrap(),
+            ..Default::default()
+        };
+        let id = v4_pool_id(&key);
+        let tick = I24::try_from(-120).unwrap();
+        let net: i128 = -5_000;
+
+        let mut snapshot = StateSnapshot::new(1);
+        let storage = &mut snapshot
+            .accounts
+            .entry(manager)
+            .or_default()
+            .storage;
+        // price 1, tick -1 sign extended from 24 bits
+        let slot0 = (U256::ONE << 96) | (U256::from(0xff_ffffu32) << 160);
+        storage.insert(v4_state_slot(id), slot0);
+        storage.insert(
+            v4_state_slot(id) + U256::from(V4_LIQUIDITY_OFFSET),
+            U256::from(7_000),
+        );
+        // tick -120 is compressed tick -2, bit 254 of word -1
+        storage.insert(v4_bitmap_slot(id, -1), U256::ONE << 254);
+        storage.insert(
+            v4_tick_slot(id, tick),
+            (U256::from(net as u128) << 128) | U256::from(5_000),
+        );
+
+        let state = snapshot.v4_state(&manager, &key, &[-1]).unwrap();
+        assert_eq!(state.x96price, U256::ONE << 96);
+        assert_eq!(state.tick, I24::try_from(-1).unwrap());
+        assert_eq!(state.liquidity, U256::from(7_000));
+        assert_eq!(state.ticks.get(0).unwrap().tick, tick);
+        assert_eq!(state.ticks.get(0).unwrap().liquidity_net, Some(net));
+
+        // the V3 layout does not see the manager's pool
+        assert!(snapshot
+            .v3_state(&manager, key.tickspacing, &[-1])
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn records_pair_token_balances() {
+        let (asserter, provider) = mocked();
+        let mut recorder = StateRecorder::new(provider, 10);
+        let pair = Address::repeat_byte(1);
+        let (token0, token1) = (Address::repeat_byte(2), Address::repeat_byte(3));
+        let implementation = Address::repeat_byte(4);
+        let code = Bytes::from(vec![
+            0x60, 0x00,
+        ]);
+        let reserves = U256::from(5) | (U256::from(9) << 112);
+
+        // pair code and reserves, then the tokens it names
+        asserter.push_success(&code);
+        asserter.push_success(&reserves);
+        push_call::<token0Call>(&asserter, &token0);
+        push_call::<token1Call>(&asserter, &token1);
+
+        // token0 is a proxy, its first slot points at the implementation
+        let proxy_slot = U256::from(7);
+        let balance_slot0 = U256::from(100);
+        push_call::<balanceOfCall>(&asserter, &U256::from(5));
+        asserter.push_success(&AccessListResult {
+            access_list: AccessList(vec![
+                AccessListItem {
+                    address: token0,
+                    storage_keys: vec![
+                        proxy_slot.into(),
+                        balance_slot0.into(),
+                    ],
+                },
+                AccessListItem {
+                    address: implementation,
+                    storage_keys: Vec::new(),
+                },
+            ]),
+            gas_used: U256::ZERO,
+            error: None,
+        });
+        asserter.push_success(&code);
+        asserter.push_success(&U256::from_be_slice(implementation.as_slice()));
+        asserter.push_success(&U256::from(5));
+        asserter.push_success(&code);
+
+        let balance_slot1 = U256::from(200);
+        push_call::<balanceOfCall>(&asserter, &U256::from(9));
+        asserter.push_success(&AccessListResult {
+            access_list: AccessList(vec![
+                AccessListItem {
+                    address: token1,
+                    storage_keys: vec![balance_slot1.into()],
+                },
+            ]),
+            gas_used: U256::ZERO,
+            error: None,
+        });
+        asserter.push_success(&code);
+        asserter.push_success(&U256::from(9));
+
+        recorder.record_v2_pair(pair).await.unwrap();
+        let snapshot = &recorder.snapshot;
+
+        let state = snapshot.v2_state(&pair).unwrap();
+        assert_eq!(state.reserves0, U256::from(5));
+        assert_eq!(snapshot.tokens_of(&pair), vec![token0, token1]);
+        assert_eq!(snapshot.balance_slot(&token0, &pair), Some(balance_slot0));
+        assert_eq!(snapshot.balance_slot(&token1, &pair), Some(balance_slot1));
+        assert!(snapshot.accounts.contains_key(&implementation));
+
+        let call = SwapCall {
+            kind: ProtocolKind::UniswapV2,
+            pool: pair,
+            v4_key: None,
+            from0: false,
+            amount_in: U256::from(3),
+        };
+        let credit = evm::v2_input_credit(snapshot, &call).unwrap();
+        assert_eq!(
+            credit,
+            evm::InputCredit {
+                token: token1,
+                slot: balance_slot1,
+                balance: U256::from(12),
+            }
+        );
+    }
+}
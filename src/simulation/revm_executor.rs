@@ -0,0 +1,268 @@
+use alloy::primitives::{
+    Address,
+    Bytes,
+    U256,
+};
+use revm::{
+    context::{
+        result::ExecutionResult,
+        Context,
+        TxEnv,
+    },
+    database::{
+        CacheDB,
+        EmptyDB,
+    },
+    primitives::TxKind,
+    state::{
+        AccountInfo,
+        Bytecode,
+    },
+    ExecuteEvm,
+    MainBuilder,
+    MainContext,
+};
+
+use crate::{
+    config::ProtocolKind,
+    err::SimulationError,
+    simulation::{
+        evm::{
+            decode_output,
+            pair_swap_calldata,
+            swap_calldata,
+            v2_input_credit,
+            SWAPPER,
+            SWAPPER_CODE,
+        },
+        StateSnapshot,
+        SwapCall,
+        SwapExecutor,
+    },
+};
+
+const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// Runs the real V2 pair, V3 pool and V4 PoolManager bytecode of the snapshot in revm.
+/// Only snapshot accounts exist: V2 and V3 swaps read the pool's token balances, so
+/// record them with `record_v2_pair`/`record_v3_pool`, and every hook a V4 key names
+/// must be recorded. V2 pairs take the output as a parameter, the executor credits
+/// the input to the pair and searches the largest output the pair accepts.
+#[derive(Debug, Clone, Copy)]
+pub struct RevmExecutor {
+    pub gas_limit: u64,
+}
+
+impl Default for RevmExecutor {
+    fn default() -> Self {
+        Self {
+            gas_limit: DEFAULT_GAS_LIMIT,
+        }
+    }
+}
+
+impl SwapExecutor for RevmExecutor {
+    fn execute(
+        &mut self,
+        snapshot: &StateSnapshot,
+        call: &SwapCall,
+    ) -> Result<U256, SimulationError> {
+        if call.kind == ProtocolKind::UniswapV2 {
+            return self.execute_v2(snapshot, call);
+        }
+
+        let data = swap_calldata(call)?;
+        match self.run(database(snapshot)?, snapshot.block, call.pool, data)? {
+            ExecutionResult::Revert {
+                output,
+                ..
+            } => decode_output(call, &output),
+            other => Err(SimulationError::Execution(format!("{other:?}"))),
+        }
+    }
+}
+
+impl RevmExecutor {
+    /// Sends `data` from `SWAPPER` to `to` on a fresh copy of `db`
+    fn run(
+        &self,
+        db: CacheDB<EmptyDB>,
+        block: u64,
+        to: Address,
+        data: Bytes,
+    ) -> Result<ExecutionResult, SimulationError> {
+        let mut evm = Context::mainnet()
+            .with_db(db)
+            .modify_block_chained(|b| b.number = block.into())
+            // `SWAPPER` has code and sends the transaction itself
+            .modify_cfg_chained(|cfg| cfg.disable_eip3607 = true)
+            .build_mainnet();
+        let tx = TxEnv {
+            caller: SWAPPER,
+            kind: TxKind::Call(to),
+            data,
+            gas_limit: self.gas_limit,
+            ..Default::default()
+        };
+
+        Ok(evm
+            .transact(tx)
+            .map_err(|e| SimulationError::Execution(format!("{e:?}")))?
+            .result)
+    }
+
+    /// Binary search over `amount_out` below the output reserve. Every output up to
+    /// the real one passes the pair's `K` check, so the largest success is the output.
+    fn execute_v2(
+        &self,
+        snapshot: &StateSnapshot,
+        call: &SwapCall,
+    ) -> Result<U256, SimulationError> {
+        let state = snapshot
+            .v2_state(&call.pool)
+            .ok_or(SimulationError::MissingState)?;
+        let reserve_out = if call.from0 {
+            state.reserves1
+        } else {
+            state.reserves0
+        };
+        let credit = v2_input_credit(snapshot, call)?;
+
+        let mut db = database(snapshot)?;
+        db.insert_account_storage(credit.token, credit.slot, credit.balance)
+            .map_err(|e| SimulationError::Execution(format!("{e:?}")))?;
+
+        // reserves are uint112, the sums can't overflow
+        let (mut low, mut high) = (U256::ZERO, reserve_out);
+        while low < high {
+            let mid = (low + high + U256::ONE) >> 1;
+            let data = pair_swap_calldata(call, mid);
+            if self
+                .run(db.clone(), snapshot.block, call.pool, data)?
+                .is_success()
+            {
+                low = mid;
+            } else {
+                high = mid - U256::ONE;
+            }
+        }
+
+        if low.is_zero() {
+            return Err(SimulationError::Execution(
+                "pair accepted no output".to_string(),
+            ));
+        }
+        Ok(low)
+    }
+}
+
+/// Snapshot accounts plus `SWAPPER`
+fn database(snapshot: &StateSnapshot) -> Result<CacheDB<EmptyDB>, SimulationError> {
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(
+        SWAPPER,
+        AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(&SWAPPER_CODE))),
+    );
+
+    for (address, account) in &snapshot.accounts {
+        db.insert_account_info(
+            *address,
+            AccountInfo::from_bytecode(Bytecode::new_raw(account.code.clone())),
+        );
+        for (slot, value) in &account.storage {
+            db.insert_account_storage(*address, *slot, *value)
+                .map_err(|e| SimulationError::Execution(format!("{e:?}")))?;
+        }
+    }
+    Ok(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::BlockId,
+        primitives::{
+            address,
+            aliases::{
+                I24,
+                U24,
+            },
+        },
+    };
+    use alloy_provider::{
+        Provider,
+        ProviderBuilder,
+    };
+
+    use super::*;
+    use crate::{
+        simulation::{
+            compare_v2,
+            compare_v3,
+            StateRecorder,
+        },
+        sol_types::V3Pool::V3PoolInstance,
+        v3_base::bitmap_math::get_pos_from_tick,
+    };
+
+    const RPC: &str = "https://ethereum-rpc.publicnode.com";
+    /// USDC/WETH on Uniswap V2 and the 0.05% Uniswap V3 pool, USDC is token0
+    const V2_PAIR: Address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+    const V3_POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+    #[tokio::test]
+    async fn recorded_swaps_match_local_math() {
+        let provider = ProviderBuilder::new().connect_http(RPC.parse().unwrap());
+        let block = provider.get_block_number().await.unwrap();
+        let mut recorder = StateRecorder::new(provider.clone(), block);
+
+        recorder.record_v2_pair(V2_PAIR).await.unwrap();
+
+        let spacing = I24::try_from(10).unwrap();
+        let slot0 = V3PoolInstance::new(V3_POOL, &provider)
+            .slot0()
+            .block(BlockId::number(block))
+            .call()
+            .await
+            .unwrap();
+        let word = get_pos_from_tick(slot0.tick, spacing);
+        let words: Vec<i16> = (word - 2..=word + 2).collect();
+        recorder
+            .record_v3_pool(V3_POOL, spacing, &words)
+            .await
+            .unwrap();
+
+        let snapshot = recorder.snapshot;
+        let mut executor = RevmExecutor::default();
+
+        // 10k USDC into the pair
+        let call = SwapCall {
+            kind: ProtocolKind::UniswapV2,
+            pool: V2_PAIR,
+            v4_key: None,
+            from0: true,
+            amount_in: U256::from(10_000_000_000u64),
+        };
+        let v2 = compare_v2(&snapshot, &mut executor, &call, 3000).unwrap();
+        assert_eq!(v2.local, v2.reference);
+
+        // 1 WETH into the V3 pool
+        let call = SwapCall {
+            kind: ProtocolKind::UniswapV3,
+            pool: V3_POOL,
+            v4_key: None,
+            from0: false,
+            amount_in: U256::from(10u128.pow(18)),
+        };
+        let v3 = compare_v3(
+            &snapshot,
+            &mut executor,
+            &call,
+            U24::from(500),
+            spacing,
+            &words,
+        )
+        .unwrap();
+        assert!(v3.matches(1), "{v3:?}");
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    rpc::types::{TransactionInput, TransactionRequest},
+};
+
+pub mod v2;
+pub mod v3;
+pub mod v4;
+
+const BPS: u32 = 10_000;
+
+/// Protection applied to every built swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapSettings {
+    /// Accepted difference from the quote, in basis points
+    pub slippage_bps: u32,
+    /// Unix timestamp after which the router rejects the swap
+    pub deadline: U256,
+}
+
+impl SwapSettings {
+    pub fn new(slippage_bps: u32, deadline: u64) -> Self {
+        Self {
+            slippage_bps: slippage_bps.min(BPS),
+            deadline: U256::from(deadline),
+        }
+    }
+
+    /// Deadline `ttl` from the local clock
+    pub fn from_now(slippage_bps: u32, ttl: Duration) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::new(slippage_bps, (now + ttl).as_secs())
+    }
+
+    /// Smallest output accepted for a quoted output, rounded down
+    pub fn min_amount_out(&self, quoted_out: U256) -> U256 {
+        quoted_out * U256::from(BPS - self.slippage_bps) / U256::from(BPS)
+    }
+
+    /// Largest input accepted for a quoted input, rounded up
+    pub fn max_amount_in(&self, quoted_in: U256) -> U256 {
+        (quoted_in * U256::from(BPS + self.slippage_bps)).div_ceil(U256::from(BPS))
+    }
+}
+
+/// Plain call to `to` with `data`
+pub(crate) fn call_request(to: Address, data: Vec<u8>) -> TransactionRequest {
+    TransactionRequest::default()
+        .to(to)
+        .input(TransactionInput::new(Bytes::from(data)))
+}
@@ -0,0 +1,251 @@
+use alloy::{
+    primitives::{
+        Address,
+        Bytes,
+        U256,
+    },
+    rpc::types::TransactionRequest,
+};
+use alloy_sol_types::SolCall;
+
+use crate::{
+    any_trade::UniTrade,
+    sol_types::{
+        IUniswapV2Pair::swapCall,
+        IUniswapV2Router02::{
+            swapExactETHForTokensCall,
+            swapExactETHForTokensSupportingFeeOnTransferTokensCall,
+            swapExactTokensForETHCall,
+            swapExactTokensForETHSupportingFeeOnTransferTokensCall,
+            swapExactTokensForTokensCall,
+            swapExactTokensForTokensSupportingFeeOnTransferTokensCall,
+            swapTokensForExactTokensCall,
+        },
+        IERC20::transferCall,
+    },
+    swap::{
+        call_request,
+        SwapSettings,
+    },
+    v3_base::states::TradeReceipt,
+};
+
+/// Direct swap against a pair, the input has to reach the pair before `swap` runs.
+/// Send both in one bundle or from a contract, the pair enforces no deadline. Works
+/// for any V2 fork since no router is involved.
+#[derive(Debug, Clone)]
+pub struct PairSwap {
+    /// ERC-20 transfer of the input to the pair
+    pub transfer: TransactionRequest,
+    /// `swap` asking for the quoted output less slippage
+    pub swap: TransactionRequest,
+}
+
+/// Builds a direct pair swap from a V2 trade receipt. Returns `None` for receipts of
+/// other pool kinds.
+pub fn pair_swap(
+    receipt: &TradeReceipt,
+    recipient: Address,
+    settings: &SwapSettings,
+) -> Option<PairSwap> {
+    if !matches!(receipt.trade, UniTrade::V2(_)) {
+        return None;
+    }
+
+    let token_in = if receipt.from0 {
+        receipt.token0
+    } else {
+        receipt.token1
+    };
+    let amount_out = settings.min_amount_out(receipt.amount_out);
+    let (amount0_out, amount1_out) = if receipt.from0 {
+        (U256::ZERO, amount_out)
+    } else {
+        (amount_out, U256::ZERO)
+    };
+
+    let transfer = transferCall {
+        to: receipt.pool,
+        amount: receipt.amount_in,
+    };
+    let swap = swapCall {
+        amount0Out: amount0_out,
+        amount1Out: amount1_out,
+        to: recipient,
+        data: Bytes::new(),
+    };
+
+    Some(PairSwap {
+        transfer: call_request(token_in, transfer.abi_encode()),
+        swap: call_request(receipt.pool, swap.abi_encode()),
+    })
+}
+
+/// Router02 `swapExactTokensForTokens`, or its fee-on-transfer variant which checks
+/// the balance received instead of the computed amounts
+pub fn swap_exact_tokens_for_tokens(
+    router: Address,
+    path: Vec<Address>,
+    amount_in: U256,
+    quoted_out: U256,
+    recipient: Address,
+    settings: &SwapSettings,
+    fee_on_transfer: bool,
+) -> TransactionRequest {
+    let min_out = settings.min_amount_out(quoted_out);
+
+    let data = if fee_on_transfer {
+        swapExactTokensForTokensSupportingFeeOnTransferTokensCall {
+            amountIn: amount_in,
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    } else {
+        swapExactTokensForTokensCall {
+            amountIn: amount_in,
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    };
+
+    call_request(router, data)
+}
+
+/// Router02 `swapTokensForExactTokens`, spending at most the quoted input plus slippage
+pub fn swap_tokens_for_exact_tokens(
+    router: Address,
+    path: Vec<Address>,
+    amount_out: U256,
+    quoted_in: U256,
+    recipient: Address,
+    settings: &SwapSettings,
+) -> TransactionRequest {
+    let call = swapTokensForExactTokensCall {
+        amountOut: amount_out,
+        amountInMax: settings.max_amount_in(quoted_in),
+        path,
+        to: recipient,
+        deadline: settings.deadline,
+    };
+
+    call_request(router, call.abi_encode())
+}
+
+/// Router02 `swapExactETHForTokens`, `path` starts with the wrapped native token and
+/// the input is sent as value
+pub fn swap_exact_eth_for_tokens(
+    router: Address,
+    path: Vec<Address>,
+    amount_in: U256,
+    quoted_out: U256,
+    recipient: Address,
+    settings: &SwapSettings,
+    fee_on_transfer: bool,
+) -> TransactionRequest {
+    let min_out = settings.min_amount_out(quoted_out);
+
+    let data = if fee_on_transfer {
+        swapExactETHForTokensSupportingFeeOnTransferTokensCall {
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    } else {
+        swapExactETHForTokensCall {
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    };
+
+    call_request(router, data).value(amount_in)
+}
+
+/// Router02 `swapExactTokensForETH`, `path` ends with the wrapped native token which
+/// is unwrapped to the recipient
+pub fn swap_exact_tokens_for_eth(
+    router: Address,
+    path: Vec<Address>,
+    amount_in: U256,
+    quoted_out: U256,
+    recipient: Address,
+    settings: &SwapSettings,
+    fee_on_transfer: bool,
+) -> TransactionRequest {
+    let min_out = settings.min_amount_out(quoted_out);
+
+    let data = if fee_on_transfer {
+        swapExactTokensForETHSupportingFeeOnTransferTokensCall {
+            amountIn: amount_in,
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    } else {
+        swapExactTokensForETHCall {
+            amountIn: amount_in,
+            amountOutMin: min_out,
+            path,
+            to: recipient,
+            deadline: settings.deadline,
+        }
+        .abi_encode()
+    };
+
+    call_request(router, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::TxKind;
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{
+        pool::UniPool,
+        routing::tests::{
+            pair,
+            E18,
+        },
+    };
+
+    #[test]
+    fn pair_swap_amounts() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let mut pool = pair(provider, 1, 2, 1_000, 2_000);
+        let receipt = pool.trade(U256::from(E18), false).unwrap();
+        let recipient = Address::repeat_byte(0xbb);
+        let settings = SwapSettings::new(50, 1_000);
+
+        let swap = pair_swap(&receipt, recipient, &settings).unwrap();
+
+        // the input goes from token1 to the pair
+        assert_eq!(swap.transfer.to, Some(TxKind::Call(receipt.token1)));
+        let transfer =
+            transferCall::abi_decode(swap.transfer.input.input().unwrap()).unwrap();
+        assert_eq!(transfer.to, receipt.pool);
+        assert_eq!(transfer.amount, U256::from(E18));
+
+        // the pair pays out token0 less the allowed slippage
+        assert_eq!(swap.swap.to, Some(TxKind::Call(receipt.pool)));
+        let call = swapCall::abi_decode(swap.swap.input.input().unwrap()).unwrap();
+        assert_eq!(call.amount0Out, settings.min_amount_out(receipt.amount_out));
+        assert!(call.amount0Out < receipt.amount_out);
+        assert_eq!(call.amount1Out, U256::ZERO);
+        assert_eq!(call.to, recipient);
+        assert!(call.data.is_empty());
+    }
+}
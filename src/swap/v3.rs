@@ -0,0 +1,526 @@
+use alloy::{
+    primitives::{
+        aliases::U24,
+        Address,
+        Bytes,
+        U160,
+        U256,
+    },
+    rpc::types::TransactionRequest,
+};
+use alloy_provider::Provider;
+use alloy_sol_types::SolCall;
+
+use crate::{
+    any_pool::V4Key,
+    any_trade::UniTrade,
+    config::DexConfig,
+    err::TradeError,
+    sol_types::{
+        IQuoterV2::{
+            quoteExactInputCall,
+            quoteExactInputReturn,
+            quoteExactInputSingleCall,
+            quoteExactInputSingleReturn,
+            quoteExactOutputCall,
+            quoteExactOutputReturn,
+            IQuoterV2Instance,
+            QuoteExactInputSingleParams,
+        },
+        ISwapRouter,
+        ISwapRouter02,
+    },
+    swap::{
+        call_request,
+        SwapSettings,
+    },
+    v3_base::states::TradeReceipt,
+    v3_pool::V3Pool,
+};
+
+/// Tokens and fees of a multi-hop V3 route, `tokens.len() == fees.len() + 1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V3Route {
+    pub tokens: Vec<Address>,
+    pub fees: Vec<U24>,
+}
+
+impl V3Route {
+    /// Walks `keys` from `token_in`, every key has to hold the token the previous
+    /// hop ends with
+    pub fn from_keys<'a>(
+        token_in: Address,
+        keys: impl IntoIterator<Item = &'a V4Key>,
+    ) -> Option<Self> {
+        let mut tokens = vec![token_in];
+        let mut fees = Vec::new();
+
+        for key in keys {
+            let current = *tokens.last().expect("route starts with a token");
+            let next = if key.currency0 == current {
+                key.currency1
+            } else if key.currency1 == current {
+                key.currency0
+            } else {
+                return None;
+            };
+            tokens.push(next);
+            fees.push(key.fee);
+        }
+
+        if fees.is_empty() {
+            return None;
+        }
+        Some(Self {
+            tokens,
+            fees,
+        })
+    }
+
+    pub fn from_pools<P: Provider>(
+        token_in: Address,
+        pools: &[&V3Pool<P>],
+    ) -> Option<Self> {
+        Self::from_keys(token_in, pools.iter().map(|p| &p.key))
+    }
+
+    /// Parses a packed path, the inverse of `encode`
+    pub fn decode(path: &[u8]) -> Option<Self> {
+        const HOP: usize = 23;
+        if path.len() < 20 + HOP || !(path.len() - 20).is_multiple_of(HOP) {
+            return None;
+        }
+
+        let mut tokens = vec![Address::from_slice(&path[..20])];
+        let mut fees = Vec::new();
+        for hop in path[20..].chunks(HOP) {
+            fees.push(U24::from_be_slice(&hop[..3]));
+            tokens.push(Address::from_slice(&hop[3..]));
+        }
+        Some(Self {
+            tokens,
+            fees,
+        })
+    }
+
+    /// The same pools walked from the output token back to the input
+    pub fn reversed(&self) -> Self {
+        Self {
+            tokens: self.tokens.iter().rev().copied().collect(),
+            fees: self.fees.iter().rev().copied().collect(),
+        }
+    }
+
+    pub fn token_in(&self) -> Address {
+        self.tokens[0]
+    }
+
+    pub fn token_out(&self) -> Address {
+        *self
+            .tokens
+            .last()
+            .expect("route holds at least two tokens")
+    }
+
+    /// Packed `token | fee | token | ...` path used by `exactInput` and the quoter
+    pub fn encode(&self) -> Bytes {
+        encode_path(self.tokens.iter(), self.fees.iter())
+    }
+
+    /// Path from the output token back to the input, used by `exactOutput`
+    pub fn encode_reversed(&self) -> Bytes {
+        encode_path(self.tokens.iter().rev(), self.fees.iter().rev())
+    }
+}
+
+fn encode_path<'a>(
+    tokens: impl Iterator<Item = &'a Address>,
+    fees: impl Iterator<Item = &'a U24>,
+) -> Bytes {
+    let mut path = Vec::new();
+    let mut fees = fees;
+    for token in tokens {
+        path.extend_from_slice(token.as_slice());
+        if let Some(fee) = fees.next() {
+            path.extend_from_slice(&fee.to_be_bytes::<3>());
+        }
+    }
+    Bytes::from(path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V3RouterKind {
+    /// Original SwapRouter, the deadline is part of every params struct
+    SwapRouter,
+    /// SwapRouter02 and PancakeSwap SmartRouter, calls are wrapped in
+    /// `multicall(deadline, data)` to keep the deadline
+    SwapRouter02,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V3Router {
+    pub address: Address,
+    pub kind: V3RouterKind,
+}
+
+impl V3Router {
+    pub fn new(address: Address, kind: V3RouterKind) -> Self {
+        Self {
+            address,
+            kind,
+        }
+    }
+
+    /// The preset routers are all SwapRouter02 style
+    pub fn from_config(dex: &DexConfig) -> Option<Self> {
+        Some(Self::new(dex.router?, V3RouterKind::SwapRouter02))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn exact_input_single(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+        amount_in: U256,
+        quoted_out: U256,
+        recipient: Address,
+        settings: &SwapSettings,
+    ) -> TransactionRequest {
+        let min_out = settings.min_amount_out(quoted_out);
+
+        let data = match self.kind {
+            V3RouterKind::SwapRouter => ISwapRouter::exactInputSingleCall {
+                params: ISwapRouter::ExactInputSingleParams {
+                    tokenIn: token_in,
+                    tokenOut: token_out,
+                    fee,
+                    recipient,
+                    deadline: settings.deadline,
+                    amountIn: amount_in,
+                    amountOutMinimum: min_out,
+                    sqrtPriceLimitX96: U160::ZERO,
+                },
+            }
+            .abi_encode(),
+            V3RouterKind::SwapRouter02 => ISwapRouter02::exactInputSingleCall {
+                params: ISwapRouter02::ExactInputSingleParams {
+                    tokenIn: token_in,
+                    tokenOut: token_out,
+                    fee,
+                    recipient,
+                    amountIn: amount_in,
+                    amountOutMinimum: min_out,
+                    sqrtPriceLimitX96: U160::ZERO,
+                },
+            }
+            .abi_encode(),
+        };
+
+        self.request(data, settings)
+    }
+
+    /// Single pool swap matching a V3 trade receipt. Returns `None` for receipts of
+    /// other pool kinds.
+    pub fn exact_input_single_from_receipt(
+        &self,
+        receipt: &TradeReceipt,
+        recipient: Address,
+        settings: &SwapSettings,
+    ) -> Option<TransactionRequest> {
+        if !matches!(receipt.trade, UniTrade::V3(_)) {
+            return None;
+        }
+        let (token_in, token_out) = if receipt.from0 {
+            (receipt.token0, receipt.token1)
+        } else {
+            (receipt.token1, receipt.token0)
+        };
+        Some(self.exact_input_single(
+            token_in,
+            token_out,
+            receipt.fee,
+            receipt.amount_in,
+            receipt.amount_out,
+            recipient,
+            settings,
+        ))
+    }
+
+    pub fn exact_input(
+        &self,
+        route: &V3Route,
+        amount_in: U256,
+        quoted_out: U256,
+        recipient: Address,
+        settings: &SwapSettings,
+    ) -> TransactionRequest {
+        let min_out = settings.min_amount_out(quoted_out);
+
+        let data = match self.kind {
+            V3RouterKind::SwapRouter => ISwapRouter::exactInputCall {
+                params: ISwapRouter::ExactInputParams {
+                    path: route.encode(),
+                    recipient,
+                    deadline: settings.deadline,
+                    amountIn: amount_in,
+                    amountOutMinimum: min_out,
+                },
+            }
+            .abi_encode(),
+            V3RouterKind::SwapRouter02 => ISwapRouter02::exactInputCall {
+                params: ISwapRouter02::ExactInputParams {
+                    path: route.encode(),
+                    recipient,
+                    amountIn: amount_in,
+                    amountOutMinimum: min_out,
+                },
+            }
+            .abi_encode(),
+        };
+
+        self.request(data, settings)
+    }
+
+    pub fn exact_output(
+        &self,
+        route: &V3Route,
+        amount_out: U256,
+        quoted_in: U256,
+        recipient: Address,
+        settings: &SwapSettings,
+    ) -> TransactionRequest {
+        let max_in = settings.max_amount_in(quoted_in);
+
+        let data = match self.kind {
+            V3RouterKind::SwapRouter => ISwapRouter::exactOutputCall {
+                params: ISwapRouter::ExactOutputParams {
+                    path: route.encode_reversed(),
+                    recipient,
+                    deadline: settings.deadline,
+                    amountOut: amount_out,
+                    amountInMaximum: max_in,
+                },
+            }
+            .abi_encode(),
+            V3RouterKind::SwapRouter02 => ISwapRouter02::exactOutputCall {
+                params: ISwapRouter02::ExactOutputParams {
+                    path: route.encode_reversed(),
+                    recipient,
+                    amountOut: amount_out,
+                    amountInMaximum: max_in,
+                },
+            }
+            .abi_encode(),
+        };
+
+        self.request(data, settings)
+    }
+
+    fn request(&self, data: Vec<u8>, settings: &SwapSettings) -> TransactionRequest {
+        match self.kind {
+            V3RouterKind::SwapRouter => call_request(self.address, data),
+            V3RouterKind::SwapRouter02 => {
+                let call = ISwapRouter02::multicall_0Call {
+                    deadline: settings.deadline,
+                    data: vec![Bytes::from(data)],
+                };
+                call_request(self.address, call.abi_encode())
+            }
+        }
+    }
+}
+
+/// QuoterV2 calls. The quoter reverts internally and is not a view, so quotes have
+/// to go through `eth_call`.
+pub struct V3Quoter<P: Provider> {
+    pub contract: IQuoterV2Instance<P>,
+}
+
+impl<P: Provider> V3Quoter<P> {
+    pub fn new(address: Address, provider: P) -> Self {
+        Self {
+            contract: IQuoterV2Instance::new(address, provider),
+        }
+    }
+
+    pub fn from_config(dex: &DexConfig, provider: P) -> Option<Self> {
+        Some(Self::new(dex.quoter?, provider))
+    }
+
+    pub fn exact_input_single_request(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+        amount_in: U256,
+    ) -> TransactionRequest {
+        let call = quoteExactInputSingleCall {
+            params: QuoteExactInputSingleParams {
+                tokenIn: token_in,
+                tokenOut: token_out,
+                amountIn: amount_in,
+                fee,
+                sqrtPriceLimitX96: U160::ZERO,
+            },
+        };
+        call_request(*self.contract.address(), call.abi_encode())
+    }
+
+    pub fn exact_input_request(
+        &self,
+        route: &V3Route,
+        amount_in: U256,
+    ) -> TransactionRequest {
+        let call = quoteExactInputCall {
+            path: route.encode(),
+            amountIn: amount_in,
+        };
+        call_request(*self.contract.address(), call.abi_encode())
+    }
+
+    pub fn exact_output_request(
+        &self,
+        route: &V3Route,
+        amount_out: U256,
+    ) -> TransactionRequest {
+        let call = quoteExactOutputCall {
+            path: route.encode_reversed(),
+            amountOut: amount_out,
+        };
+        call_request(*self.contract.address(), call.abi_encode())
+    }
+
+    pub async fn quote_exact_input_single(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+        amount_in: U256,
+    ) -> Result<quoteExactInputSingleReturn, alloy_contract::Error> {
+        let params = QuoteExactInputSingleParams {
+            tokenIn: token_in,
+            tokenOut: token_out,
+            amountIn: amount_in,
+            fee,
+            sqrtPriceLimitX96: U160::ZERO,
+        };
+        self.contract
+            .quoteExactInputSingle(params)
+            .call()
+            .await
+    }
+
+    pub async fn quote_exact_input(
+        &self,
+        route: &V3Route,
+        amount_in: U256,
+    ) -> Result<quoteExactInputReturn, alloy_contract::Error> {
+        self.contract
+            .quoteExactInput(route.encode(), amount_in)
+            .call()
+            .await
+    }
+
+    pub async fn quote_exact_output(
+        &self,
+        route: &V3Route,
+        amount_out: U256,
+    ) -> Result<quoteExactOutputReturn, alloy_contract::Error> {
+        self.contract
+            .quoteExactOutput(route.encode_reversed(), amount_out)
+            .call()
+            .await
+    }
+
+    /// Quotes the receipt's swap on chain, returns the on chain output. Receipts of
+    /// other pool kinds fail with `TradeError::Receipt`.
+    pub async fn check_receipt(
+        &self,
+        receipt: &TradeReceipt,
+    ) -> Result<U256, TradeError> {
+        if !matches!(receipt.trade, UniTrade::V3(_)) {
+            return Err(TradeError::Receipt);
+        }
+        let (token_in, token_out) = if receipt.from0 {
+            (receipt.token0, receipt.token1)
+        } else {
+            (receipt.token1, receipt.token0)
+        };
+        let quote = self
+            .quote_exact_input_single(token_in, token_out, receipt.fee, receipt.amount_in)
+            .await?;
+        Ok(quote.amountOut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{
+        address,
+        hex,
+    };
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{
+        pool::UniPool,
+        routing::tests::{
+            pair,
+            E18,
+        },
+    };
+
+    #[test]
+    fn packed_path() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2E9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let route = V3Route {
+            tokens: vec![
+                usdc, weth,
+            ],
+            fees: vec![U24::from(500)],
+        };
+
+        assert_eq!(
+            route.encode(),
+            Bytes::from(hex!(
+                "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb480001f4c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+            ))
+        );
+        assert_eq!(
+            route.encode_reversed(),
+            Bytes::from(hex!(
+                "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20001f4a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+            ))
+        );
+        assert_eq!(V3Route::decode(&route.encode()), Some(route.clone()));
+        assert_eq!(
+            V3Route::decode(&route.encode_reversed()),
+            Some(route.reversed())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_other_receipts() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let receipt = pair(provider.clone(), 1, 2, 1_000, 1_000)
+            .trade(U256::from(E18), true)
+            .unwrap();
+        let settings = SwapSettings::new(50, 1_000);
+
+        let router =
+            V3Router::new(Address::repeat_byte(0xaa), V3RouterKind::SwapRouter02);
+        assert!(router
+            .exact_input_single_from_receipt(&receipt, Address::ZERO, &settings)
+            .is_none());
+
+        // rejected before any request reaches the node
+        let quoter = V3Quoter::new(Address::repeat_byte(0xbb), provider);
+        assert!(matches!(
+            quoter.check_receipt(&receipt).await,
+            Err(TradeError::Receipt)
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+use alloy::{
+    primitives::{
+        address,
+        uint,
+        Address,
+        Bytes,
+        U256,
+    },
+    rpc::types::TransactionRequest,
+};
+use alloy_provider::Provider;
+use alloy_sol_types::{
+    SolCall,
+    SolValue,
+};
+
+use crate::{
+    any_pool::V4Key,
+    any_trade::UniTrade,
+    config::DexConfig,
+    err::SwapError,
+    sol_types::{
+        IUniversalRouter::execute_0Call,
+        IV4Router::{
+            ExactInputSingleParams,
+            ExactOutputSingleParams,
+        },
+    },
+    swap::{
+        call_request,
+        SwapSettings,
+    },
+    v3_base::states::TradeReceipt,
+    v4_pool::V4Pool,
+};
+
+/// Universal Router command running a V4Router action list
+pub const V4_SWAP: u8 = 0x10;
+/// Universal Router command sending the router balance of a token out
+pub const SWEEP: u8 = 0x04;
+/// Universal Router command moving tokens of the caller through Permit2
+pub const PERMIT2_TRANSFER_FROM: u8 = 0x02;
+/// Universal Router command sending tokens the router holds
+pub const TRANSFER: u8 = 0x05;
+/// Universal Router V3 and V2 swap commands
+pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+pub const V3_SWAP_EXACT_OUT: u8 = 0x01;
+pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+pub const V2_SWAP_EXACT_OUT: u8 = 0x09;
+/// Bits of a command byte holding the command, the high bit allows it to revert
+pub const COMMAND_MASK: u8 = 0x3f;
+
+pub const SWAP_EXACT_IN_SINGLE: u8 = 0x06;
+pub const SWAP_EXACT_OUT_SINGLE: u8 = 0x08;
+pub const SETTLE_ALL: u8 = 0x0c;
+pub const TAKE_ALL: u8 = 0x0f;
+
+/// Recipient placeholder the router replaces with `msg.sender`
+pub const MSG_SENDER: Address = address!("0000000000000000000000000000000000000001");
+
+/// Universal Router amount standing for the router's whole balance of the token
+pub const CONTRACT_BALANCE: U256 =
+    uint!(0x8000000000000000000000000000000000000000000000000000000000000000_U256);
+
+/// Native currency in V4 pool keys
+pub const NATIVE: Address = Address::ZERO;
+
+/// V4Router action list, encoded as the `V4_SWAP` command input
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct V4Actions {
+    pub actions: Vec<u8>,
+    pub params: Vec<Bytes>,
+}
+
+impl V4Actions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails with `SwapError::AmountOverflow` when an amount exceeds `uint128`
+    pub fn swap_exact_in_single(
+        self,
+        key: &V4Key,
+        zero_for_one: bool,
+        amount_in: U256,
+        min_out: U256,
+        hook_data: Bytes,
+    ) -> Result<Self, SwapError> {
+        let params = ExactInputSingleParams {
+            poolKey: (*key).into(),
+            zeroForOne: zero_for_one,
+            amountIn: to_u128(amount_in)?,
+            amountOutMinimum: to_u128(min_out)?,
+            hookData: hook_data,
+        };
+        Ok(self.push(SWAP_EXACT_IN_SINGLE, params.abi_encode()))
+    }
+
+    /// Fails with `SwapError::AmountOverflow` when an amount exceeds `uint128`
+    pub fn swap_exact_out_single(
+        self,
+        key: &V4Key,
+        zero_for_one: bool,
+        amount_out: U256,
+        max_in: U256,
+        hook_data: Bytes,
+    ) -> Result<Self, SwapError> {
+        let params = ExactOutputSingleParams {
+            poolKey: (*key).into(),
+            zeroForOne: zero_for_one,
+            amountOut: to_u128(amount_out)?,
+            amountInMaximum: to_u128(max_in)?,
+            hookData: hook_data,
+        };
+        Ok(self.push(SWAP_EXACT_OUT_SINGLE, params.abi_encode()))
+    }
+
+    /// Pays what the swaps owe in `currency`, failing above `max_amount`
+    pub fn settle_all(self, currency: Address, max_amount: U256) -> Self {
+        self.push(SETTLE_ALL, (currency, max_amount).abi_encode_params())
+    }
+
+    /// Sends what the swaps credit in `currency` to the caller, failing below
+    /// `min_amount`
+    pub fn take_all(self, currency: Address, min_amount: U256) -> Self {
+        self.push(TAKE_ALL, (currency, min_amount).abi_encode_params())
+    }
+
+    fn push(mut self, action: u8, params: Vec<u8>) -> Self {
+        self.actions.push(action);
+        self.params.push(Bytes::from(params));
+        self
+    }
+
+    /// `abi.encode(actions, params)`
+    pub fn encode(&self) -> Bytes {
+        let input = (Bytes::from(self.actions.clone()), self.params.clone());
+        Bytes::from(input.abi_encode_params())
+    }
+}
+
+/// Universal Router `execute` builder for single pool V4 swaps. ERC-20 inputs are
+/// pulled through Permit2, which has to be approved for the router beforehand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniversalRouter {
+    pub address: Address,
+}
+
+impl UniversalRouter {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+        }
+    }
+
+    pub fn from_config(dex: &DexConfig) -> Option<Self> {
+        Some(Self::new(dex.router?))
+    }
+
+    /// Sells exactly `amount_in`. A native input is sent as the transaction value.
+    pub fn exact_input_single(
+        &self,
+        key: &V4Key,
+        zero_for_one: bool,
+        amount_in: U256,
+        quoted_out: U256,
+        hook_data: Bytes,
+        settings: &SwapSettings,
+    ) -> Result<TransactionRequest, SwapError> {
+        let (currency_in, currency_out) = currencies(key, zero_for_one);
+        let min_out = settings.min_amount_out(quoted_out);
+
+        let actions = V4Actions::new()
+            .swap_exact_in_single(key, zero_for_one, amount_in, min_out, hook_data)?
+            .settle_all(currency_in, amount_in)
+            .take_all(currency_out, min_out);
+
+        let request = self.execute(vec![V4_SWAP], vec![actions.encode()], settings);
+        if currency_in == NATIVE {
+            Ok(request.value(amount_in))
+        } else {
+            Ok(request)
+        }
+    }
+
+    /// Buys exactly `amount_out`. A native input sends the maximum input as value and
+    /// sweeps what the swap did not use back to the caller.
+    pub fn exact_output_single(
+        &self,
+        key: &V4Key,
+        zero_for_one: bool,
+        amount_out: U256,
+        quoted_in: U256,
+        hook_data: Bytes,
+        settings: &SwapSettings,
+    ) -> Result<TransactionRequest, SwapError> {
+        let (currency_in, currency_out) = currencies(key, zero_for_one);
+        let max_in = settings.max_amount_in(quoted_in);
+
+        let actions = V4Actions::new()
+            .swap_exact_out_single(key, zero_for_one, amount_out, max_in, hook_data)?
+            .settle_all(currency_in, max_in)
+            .take_all(currency_out, amount_out);
+
+        if currency_in != NATIVE {
+            return Ok(self.execute(vec![V4_SWAP], vec![actions.encode()], settings));
+        }
+
+        let sweep = (NATIVE, MSG_SENDER, U256::ZERO).abi_encode_params();
+        Ok(self
+            .execute(
+                vec![
+                    V4_SWAP, SWEEP,
+                ],
+                vec![
+                    actions.encode(),
+                    Bytes::from(sweep),
+                ],
+                settings,
+            )
+            .value(max_in))
+    }
+
+    /// Exact input swap matching a quote from `pool`. Receipts of other pool kinds or
+    /// of another V4 pool fail with `SwapError::Receipt`.
+    pub fn exact_input_from_receipt<P: Provider>(
+        &self,
+        pool: &V4Pool<P>,
+        receipt: &TradeReceipt,
+        hook_data: Bytes,
+        settings: &SwapSettings,
+    ) -> Result<TransactionRequest, SwapError> {
+        if !matches!(receipt.trade, UniTrade::V4(_)) || receipt.pool_id != Some(pool.id) {
+            return Err(SwapError::Receipt);
+        }
+        self.exact_input_single(
+            &pool.key,
+            receipt.from0,
+            receipt.amount_in,
+            receipt.amount_out,
+            hook_data,
+            settings,
+        )
+    }
+
+    pub(crate) fn execute(
+        &self,
+        commands: Vec<u8>,
+        inputs: Vec<Bytes>,
+        settings: &SwapSettings,
+    ) -> TransactionRequest {
+        let call = execute_0Call {
+            commands: Bytes::from(commands),
+            inputs,
+            deadline: settings.deadline,
+        };
+        call_request(self.address, call.abi_encode())
+    }
+}
+
+/// V4Router amounts are `uint128`
+fn to_u128(amount: U256) -> Result<u128, SwapError> {
+    amount
+        .try_into()
+        .map_err(|_| SwapError::AmountOverflow)
+}
+
+/// `(input, output)` currencies of a swap direction
+fn currencies(key: &V4Key, zero_for_one: bool) -> (Address, Address) {
+    if zero_for_one {
+        (key.currency0, key.currency1)
+    } else {
+        (key.currency1, key.currency0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::aliases::{
+        I24,
+        U24,
+    };
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::{
+        mempool::{
+            Hop,
+            MempoolDecoder,
+            SwapAmount,
+        },
+        pool::{
+            SyncStatus,
+            UniPool,
+        },
+        pool_address::v4_pool_id,
+        routing::tests::{
+            pair,
+            E18,
+        },
+        sol_types::StateView::StateViewInstance,
+        v3_base::{
+            ticks::Tick,
+            v3_state::V3State,
+        },
+    };
+
+    fn v4<P: Provider>(provider: P, fee: u32) -> V4Pool<P> {
+        let key = V4Key {
+            currency0: Address::repeat_byte(1),
+            currency1: Address::repeat_byte(2),
+            fee: U24::from(fee),
+            tickspacing: I24::try_from(10).unwrap(),
+            ..Default::default()
+        };
+        let mut pool = V4Pool {
+            key,
+            id: v4_pool_id(&key),
+            state: V3State::default(key.tickspacing),
+            contract: StateViewInstance::new(Address::repeat_byte(0xcc), provider),
+            sync: SyncStatus::default(),
+        };
+        pool.state.x96price = U256::ONE << 96;
+        pool.state.liquidity = U256::from(1_000 * E18);
+        pool.state.ticks.insert_ticks(vec![
+            Tick {
+                tick: I24::try_from(-600).unwrap(),
+                liquidity_net: Some(1_000 * E18 as i128),
+            },
+            Tick {
+                tick: I24::try_from(600).unwrap(),
+                liquidity_net: Some(-(1_000 * E18 as i128)),
+            },
+        ]);
+        pool
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let router = UniversalRouter::new(Address::repeat_byte(0xaa));
+        let settings = SwapSettings::new(100, 1);
+        let key = V4Key {
+            currency0: NATIVE,
+            currency1: Address::repeat_byte(1),
+            ..Default::default()
+        };
+        let mut decoder = MempoolDecoder::new();
+        decoder.add_router(router.address, None);
+
+        // native input adds a sweep, which the decoder skips
+        let request = router
+            .exact_output_single(
+                &key,
+                true,
+                U256::from(1_000),
+                U256::from(500),
+                Bytes::new(),
+                &settings,
+            )
+            .unwrap();
+        let input = request.input.input().unwrap();
+        let call = execute_0Call::abi_decode(input).unwrap();
+        assert_eq!(call.commands[..], [V4_SWAP, SWEEP]);
+
+        let intents = decoder.decode_call(router.address, input, U256::from(505));
+        assert_eq!(intents.len(), 1);
+        assert_eq!(
+            intents[0].hops,
+            [
+                Hop::V4 {
+                    key,
+                    zero_for_one: true,
+                }
+            ]
+        );
+        assert_eq!(
+            intents[0].amount,
+            SwapAmount::ExactOut {
+                amount_out: U256::from(1_000),
+                max_in: settings.max_amount_in(U256::from(500)),
+            }
+        );
+
+        let request = router
+            .exact_input_single(
+                &key,
+                false,
+                U256::from(u128::MAX),
+                U256::from(7),
+                Bytes::new(),
+                &settings,
+            )
+            .unwrap();
+        let intents = decoder.decode_call(
+            router.address,
+            request.input.input().unwrap(),
+            U256::ZERO,
+        );
+        assert_eq!(
+            intents[0].amount,
+            SwapAmount::ExactIn {
+                amount_in: U256::from(u128::MAX),
+                min_out: settings.min_amount_out(U256::from(7)),
+            }
+        );
+
+        // amounts past uint128 are refused instead of clamped
+        let too_large = U256::from(u128::MAX) + U256::ONE;
+        assert_eq!(
+            router
+                .exact_input_single(
+                    &key,
+                    true,
+                    too_large,
+                    U256::ONE,
+                    Bytes::new(),
+                    &settings
+                )
+                .unwrap_err(),
+            SwapError::AmountOverflow
+        );
+        assert!(V4Actions::new()
+            .swap_exact_out_single(&key, true, U256::ONE, too_large, Bytes::new())
+            .is_err());
+    }
+
+    #[test]
+    fn receipt_must_match_pool() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://localhost:8545".parse().unwrap());
+        let router = UniversalRouter::new(Address::repeat_byte(0xaa));
+        let settings = SwapSettings::new(50, 1_000);
+        let mut pool = v4(provider.clone(), 3000);
+        let mut other = v4(provider.clone(), 500);
+
+        let receipt = pool.trade(U256::from(E18), true).unwrap();
+        assert!(router
+            .exact_input_from_receipt(&pool, &receipt, Bytes::new(), &settings)
+            .is_ok());
+
+        // same tokens, other fee tier
+        let other_receipt = other.trade(U256::from(E18), true).unwrap();
+        assert_eq!(
+            router
+                .exact_input_from_receipt(&pool, &other_receipt, Bytes::new(), &settings)
+                .unwrap_err(),
+            SwapError::Receipt
+        );
+
+        let v2_receipt = pair(provider, 1, 2, 1_000, 1_000)
+            .trade(U256::from(E18), true)
+            .unwrap();
+        assert_eq!(
+            router
+                .exact_input_from_receipt(&pool, &v2_receipt, Bytes::new(), &settings)
+                .unwrap_err(),
+            SwapError::Receipt
+        );
+    }
+}
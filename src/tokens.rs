@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::Path,
+};
+
+use alloy::primitives::{Address, Bytes, U256, U512};
+use alloy_provider::Provider;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ChainConfig,
+    err::DiscoveryError,
+    multicall::{self, Multicall},
+    pool::UniPool,
+    price::format_fixed,
+    sol_types::IERC20::{decimalsCall, nameCall, symbolCall},
+    v3_base::states::TradeReceipt,
+};
+
+/// Decimals assumed for tokens whose `decimals` call reverts
+pub const DEFAULT_DECIMALS: u8 = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub address: Address,
+    pub decimals: u8,
+    pub symbol: String,
+    pub name: String,
+    /// Set when `decimals` could not be read and `DEFAULT_DECIMALS` is a guess
+    #[serde(default)]
+    pub unknown: bool,
+}
+
+impl TokenInfo {
+    /// Placeholder for tokens that don't answer the metadata calls
+    pub fn unknown(address: Address) -> Self {
+        Self {
+            address,
+            decimals: DEFAULT_DECIMALS,
+            symbol: format!("{:#}", address),
+            name: String::new(),
+            unknown: true,
+        }
+    }
+
+    /// `amount` in whole tokens with `precision` fractional digits, rounded to nearest
+    pub fn format_amount(&self, amount: U256, precision: u8) -> String {
+        let ten = U512::from(10);
+        let unit = ten.pow(U512::from(self.decimals));
+        let scaled = U512::from(amount) * ten.pow(U512::from(precision));
+        format_fixed((scaled + unit / U512::from(2)) / unit, precision)
+    }
+
+    /// Parses an amount in whole tokens like `"1.5"` into raw units. Digits past the
+    /// token decimals are rejected.
+    pub fn parse_amount(&self, value: &str) -> Option<U256> {
+        let value = value.trim();
+        let (int_part, frac_part) = value.split_once('.').unwrap_or((value, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        if frac_part.len() > self.decimals as usize {
+            return None;
+        }
+        let digits = format!(
+            "{}{:0<width$}",
+            int_part,
+            frac_part,
+            width = self.decimals as usize
+        );
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        U256::from_str_radix(&digits, 10).ok()
+    }
+}
+
+/// Cache of ERC-20 metadata. Missing tokens are fetched in Multicall batches. Tokens
+/// whose `decimals` call fails are kept as `TokenInfo::unknown` placeholders, asked
+/// again on the next `fetch` and never saved.
+pub struct TokenRegistry<P: Provider> {
+    pub multicall: Multicall<P>,
+    tokens: HashMap<Address, TokenInfo>,
+}
+
+impl<P: Provider> TokenRegistry<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            multicall: Multicall::new(provider),
+            tokens: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &ChainConfig, provider: P) -> Self {
+        Self {
+            multicall: Multicall::new_with_address(config.multicall, provider),
+            tokens: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, token: &Address) -> Option<&TokenInfo> {
+        self.tokens.get(token)
+    }
+
+    pub fn insert(&mut self, info: TokenInfo) {
+        self.tokens.insert(info.address, info);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Fetches `decimals`, `symbol` and `name` of every token not cached yet or
+    /// cached as unknown
+    pub async fn fetch(&mut self, tokens: &[Address]) -> Result<(), DiscoveryError> {
+        let mut missing: Vec<Address> = tokens
+            .iter()
+            .filter(|t| self.get(t).is_none_or(|info| info.unknown))
+            .copied()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut calls = Vec::with_capacity(missing.len() * 3);
+        for token in &missing {
+            calls.push(multicall::encode(*token, &decimalsCall {}));
+            calls.push(multicall::encode(*token, &symbolCall {}));
+            calls.push(multicall::encode(*token, &nameCall {}));
+        }
+        let results = self.multicall.try_aggregate(calls).await?;
+
+        for (token, r) in missing.iter().zip(results.chunks(3)) {
+            let mut info = TokenInfo::unknown(*token);
+            if let Some(decimals) = multicall::decode::<decimalsCall>(&r[0]) {
+                info.decimals = decimals;
+                info.unknown = false;
+            }
+            if let Some(symbol) = decode_text::<symbolCall>(&r[1]) {
+                info.symbol = symbol;
+            }
+            if let Some(name) = decode_text::<nameCall>(&r[2]) {
+                info.name = name;
+            }
+            self.insert(info);
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the metadata of both tokens of every pool
+    pub async fn fetch_pool_tokens<U: UniPool>(
+        &mut self,
+        pools: &[U],
+    ) -> Result<(), DiscoveryError> {
+        let tokens: Vec<Address> = pools
+            .iter()
+            .flat_map(|p| {
+                [
+                    *p.get_a(),
+                    *p.get_b(),
+                ]
+            })
+            .collect();
+        self.fetch(&tokens).await
+    }
+
+    /// Human price of one whole token0 in whole token1 of `pool`
+    pub fn pool_price<U: UniPool>(&self, pool: &U, precision: u8) -> Option<String> {
+        let token0 = self.get(pool.get_a())?;
+        let token1 = self.get(pool.get_b())?;
+        pool.get_price()
+            .to_decimal_string(token0.decimals, token1.decimals, precision)
+    }
+
+    /// Renders a trade like `1.5000 WETH -> 3012.4410 USDC`
+    pub fn format_trade(&self, trade: &TradeReceipt, precision: u8) -> Option<String> {
+        let (token_in, token_out) = if trade.from0 {
+            (self.get(&trade.token0)?, self.get(&trade.token1)?)
+        } else {
+            (self.get(&trade.token1)?, self.get(&trade.token0)?)
+        };
+        Some(format!(
+            "{} {} -> {} {}",
+            token_in.format_amount(trade.amount_in, precision),
+            token_in.symbol,
+            token_out.format_amount(trade.amount_out, precision),
+            token_out.symbol
+        ))
+    }
+
+    /// Adds the tokens of a file written by `save`, e.g.
+    /// `TokenRegistry::from_config(&config, provider).load(path)`. Nothing is added
+    /// when the file does not exist or can't be parsed.
+    pub fn load(mut self, path: impl AsRef<Path>) -> Self {
+        if let Ok(data) = fs::read_to_string(path) {
+            let tokens: Vec<TokenInfo> = serde_json::from_str(&data).unwrap_or_default();
+            for info in tokens {
+                self.insert(info);
+            }
+        }
+        self
+    }
+
+    /// Writes every known token as a JSON list sorted by address, placeholders are
+    /// left out
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+
+        let mut tokens: Vec<&TokenInfo> = self
+            .tokens
+            .values()
+            .filter(|t| !t.unknown)
+            .collect();
+        tokens.sort_by_key(|t| t.address);
+        let data = serde_json::to_string_pretty(&tokens).map_err(io::Error::other)?;
+
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+}
+
+/// Decodes a `string` return, falling back to the `bytes32` some old tokens (MKR,
+/// SAI) return
+fn decode_text<C>(result: &Option<Bytes>) -> Option<String>
+where
+    C: alloy_sol_types::SolCall<Return = String>,
+{
+    if let Some(text) = multicall::decode::<C>(result) {
+        let text = text.trim_end_matches('\0').to_string();
+        return (!text.is_empty()).then_some(text);
+    }
+
+    let bytes = result.as_ref()?;
+    if bytes.len() != 32 {
+        return None;
+    }
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(32);
+    let text = std::str::from_utf8(&bytes[..end]).ok()?;
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_sol_types::SolValue;
+
+    use super::*;
+
+    fn usdc() -> TokenInfo {
+        TokenInfo {
+            address: Address::repeat_byte(1),
+            decimals: 6,
+            symbol: "USDC".to_string(),
+            name: "USD Coin".to_string(),
+            unknown: false,
+        }
+    }
+
+    #[test]
+    fn parse_and_format_amounts() {
+        let token = usdc();
+        assert_eq!(token.parse_amount("1.5"), Some(U256::from(1_500_000)));
+        assert_eq!(token.parse_amount(" 12 "), Some(U256::from(12_000_000)));
+        assert_eq!(token.parse_amount(".25"), Some(U256::from(250_000)));
+        assert_eq!(token.parse_amount("0.000001"), Some(U256::from(1)));
+        assert_eq!(token.parse_amount("0.0000001"), None);
+        assert_eq!(token.parse_amount(""), None);
+        assert_eq!(token.parse_amount("."), None);
+        assert_eq!(token.parse_amount("-1"), None);
+        assert_eq!(token.parse_amount("1e6"), None);
+
+        assert_eq!(token.format_amount(U256::from(1_500_000), 2), "1.50");
+        assert_eq!(token.format_amount(U256::from(1_234_567), 4), "1.2346");
+        assert_eq!(token.format_amount(U256::from(999_999), 0), "1");
+        assert_eq!(token.format_amount(U256::ZERO, 3), "0.000");
+    }
+
+    #[test]
+    fn decode_string_and_bytes32_text() {
+        let string = Some(Bytes::from("WETH".to_string().abi_encode()));
+        assert_eq!(decode_text::<symbolCall>(&string), Some("WETH".to_string()));
+
+        let mut word = [0u8; 32];
+        word[..3].copy_from_slice(b"MKR");
+        let bytes32 = Some(Bytes::from(word.to_vec()));
+        assert_eq!(decode_text::<symbolCall>(&bytes32), Some("MKR".to_string()));
+
+        assert_eq!(decode_text::<symbolCall>(&Some(Bytes::from(vec![0u8; 32]))), None);
+        assert_eq!(decode_text::<symbolCall>(&None), None);
+
+        let placeholder = TokenInfo::unknown(Address::repeat_byte(2));
+        assert!(placeholder.unknown);
+        assert_eq!(placeholder.decimals, DEFAULT_DECIMALS);
+    }
+}
@@ -1,7 +1,12 @@
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, U256, U512};
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+use crate::{
+    price::Price,
+    v3_base::depth::{target_price, Depth, DepthLevel},
+};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct V2State {
     pub reserves0: U256,
     pub reserves1: U256,
@@ -16,6 +21,69 @@ pub struct V2Key {
 }
 
 impl V2State {
+    /// Writes the reserves a trade ends with
+    pub fn apply_trade(&mut self, trade: &V2Trade) {
+        self.reserves0 = trade.new_reserves0;
+        self.reserves1 = trade.new_reserves1;
+    }
+
+    /// Amounts that move the price to `target`, before fees. Constant product
+    /// reserves at a sqrt price `s` are `sqrt(k) / s` and `sqrt(k) * s`.
+    pub fn amount_to_price(&self, target: U256) -> Option<Depth> {
+        if target.is_zero() {
+            return None;
+        }
+        let current = Price::from_amounts(self.reserves0, self.reserves1)?.to_sqrt_x96();
+        let from0 = target < current;
+
+        // sqrt(k) in Q96
+        let k: U512 = (U512::from(self.reserves0) * U512::from(self.reserves1)) << 192;
+        let root_k = k.root(2);
+        let reserves0 = U256::saturating_from(root_k / U512::from(target));
+        let reserves1 = U256::saturating_from((root_k * U512::from(target)) >> 192);
+
+        let (amount_in, amount_out) = if from0 {
+            (
+                reserves0.saturating_sub(self.reserves0),
+                self.reserves1.saturating_sub(reserves1),
+            )
+        } else {
+            (
+                reserves1.saturating_sub(self.reserves1),
+                self.reserves0.saturating_sub(reserves0),
+            )
+        };
+
+        Some(Depth {
+            from0,
+            amount_in,
+            amount_out,
+            x96price: target,
+            ticks_crossed: 0,
+        })
+    }
+
+    /// Amounts that move the price by `bps`, negative moves it down
+    pub fn amount_to_move(&self, bps: i32) -> Option<Depth> {
+        let current = Price::from_amounts(self.reserves0, self.reserves1)?.to_sqrt_x96();
+        self.amount_to_price(target_price(current, bps)?)
+    }
+
+    /// Depth at every move in `levels`, see `v3_base::depth::depth_ladder`
+    pub fn depth_ladder(&self, levels: &[u32]) -> Option<Vec<DepthLevel>> {
+        levels
+            .iter()
+            .map(|bps| {
+                let signed = i32::try_from(*bps).ok()?;
+                Some(DepthLevel {
+                    bps: *bps,
+                    down: self.amount_to_move(-signed)?,
+                    up: self.amount_to_move(signed)?,
+                })
+            })
+            .collect()
+    }
+
     pub fn trade(&self, amount_in: U256, fee: u32, from0: bool) -> Option<V2Trade> {
         if (from0 && self.reserves0 == U256::ZERO)
             || (!from0 && self.reserves1 == U256::ZERO)
@@ -29,19 +97,18 @@ impl V2State {
             false => (self.reserves1, self.reserves0),
         };
 
-        let sfee = fee / 1000;
-
-        // 3. Apply V2 fee calculation correctly (0.3% fee)
+        // 3. Apply the fee, given in hundredths of a bip (3000 = 0.3%)
         let amount_in_less_fee = amount_in
-            .checked_mul(U256::from(1000 - sfee))?
-            .checked_div(U256::from(1000))?;
+            .checked_mul(U256::from(1_000_000_u32.checked_sub(fee)?))?
+            .checked_div(U256::from(1_000_000))?;
 
         let numerator = amount_in_less_fee.checked_mul(reserve_out)?;
         let denominator = reserve_in.checked_add(amount_in_less_fee)?;
         let amount_out = numerator.checked_div(denominator)?;
         // 5. Calculate price impact with decimal adjustment
 
-        let new_reserve_in = reserve_in.checked_add(amount_in_less_fee)?;
+        // the pair keeps the fee, so the whole input lands in the reserve
+        let new_reserve_in = reserve_in.checked_add(amount_in)?;
         let new_reserve_out = reserve_out.checked_sub(amount_out)?;
 
         // Multiply numerator first to preserve precision (like fixed-point math)
@@ -70,7 +137,7 @@ impl V2State {
         })
     }
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct V2Trade {
     pub fee_amount: U256,
     pub amount_in: U256,
@@ -80,3 +147,70 @@ pub struct V2Trade {
     pub new_reserves0: U256,
     pub new_reserves1: U256,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_in_hundredths_of_a_bip() {
+        let state = V2State {
+            reserves0: U256::from(10u128.pow(21)),
+            reserves1: U256::from(2 * 10u128.pow(21)),
+        };
+        let amount = U256::from(10u128.pow(18));
+
+        let trade = state.trade(amount, 3000, true).unwrap();
+        assert_eq!(trade.fee_amount, U256::from(3 * 10u128.pow(15)));
+        let net = amount - trade.fee_amount;
+        assert_eq!(
+            trade.amount_out,
+            net * state.reserves1 / (state.reserves0 + net)
+        );
+        assert_eq!(trade.new_reserves0, state.reserves0 + amount);
+        assert_eq!(trade.new_reserves1, state.reserves1 - trade.amount_out);
+
+        let reverse = state.trade(amount, 3000, false).unwrap();
+        assert_eq!(reverse.fee_amount, trade.fee_amount);
+        assert!(reverse.amount_out < trade.amount_out);
+
+        // a 1% pool pays less than a 0.3% pool, which pays less than no fee
+        let one_percent = state.trade(amount, 10_000, true).unwrap();
+        let free = state.trade(amount, 0, true).unwrap();
+        assert!(one_percent.amount_out < trade.amount_out);
+        assert!(trade.amount_out < free.amount_out);
+        assert!(state.trade(amount, 1_000_001, true).is_none());
+    }
+
+    #[test]
+    fn depth_matches_trade() {
+        let state = V2State {
+            reserves0: U256::from(10u128.pow(21)),
+            reserves1: U256::from(2 * 10u128.pow(21)),
+        };
+
+        for bps in [
+            -100, 100,
+        ] {
+            let depth = state.amount_to_move(bps).unwrap();
+            assert_eq!(depth.from0, bps < 0);
+
+            let trade = state
+                .trade(depth.amount_in, 0, depth.from0)
+                .unwrap();
+            let diff = trade.amount_out.abs_diff(depth.amount_out);
+            assert!(
+                diff <= U256::from(2),
+                "{} vs {}",
+                trade.amount_out,
+                depth.amount_out
+            );
+
+            // the fee-free trade ends on the target price
+            let price = Price::from_amounts(trade.new_reserves0, trade.new_reserves1)
+                .unwrap()
+                .to_sqrt_x96();
+            assert!(price.abs_diff(depth.x96price) <= price >> 64);
+        }
+    }
+}
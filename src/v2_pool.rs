@@ -1,12 +1,26 @@
 use crate::{
     any_pool::AnyPool,
-    pool::UniPool,
+    any_trade::UniTrade,
+    err::{
+        StateError,
+        TradeError,
+    },
+    pool::{
+        PoolCheckpoint,
+        SyncStatus,
+        UniPool,
+    },
+    price::Price,
     sol_types::IUniswapV2Pair::{getReservesCall, IUniswapV2PairInstance},
     v2_base::{V2Key, V2State},
+    v3_base::states::TradeReceipt,
 };
 
 use alloy::{
-    primitives::{aliases::U112, Address, U256},
+    primitives::{
+        aliases::{U112, U24},
+        Address, U256,
+    },
     rpc::types::{EthCallResponse, TransactionRequest},
 };
 use alloy_provider::Provider;
@@ -16,9 +30,22 @@ pub struct V2Pool<P: Provider> {
     pub state: V2State,
     pub factory: Address,
     pub contract: IUniswapV2PairInstance<P>,
+    pub sync: SyncStatus,
 }
 
 impl<P: Provider> V2Pool<P> {
+    pub fn new_from_key(key: V2Key, factory: Address, provider: P) -> Self {
+        let contract = IUniswapV2PairInstance::new(key.address, provider);
+
+        Self {
+            key,
+            state: V2State::default(),
+            factory,
+            contract,
+            sync: SyncStatus::default(),
+        }
+    }
+
     pub async fn create_v2_from_address(
         addr: Address,
         fee: Option<u32>,
@@ -54,6 +81,7 @@ impl<P: Provider> V2Pool<P> {
             factory,
             state,
             contract,
+            sync: SyncStatus::default(),
         })
     }
 }
@@ -63,15 +91,32 @@ impl<P: Provider> UniPool for V2Pool<P> {
         &mut self,
         amount: U256,
         from0: bool,
-    ) -> Result<crate::any_trade::UniTrade, crate::err::TradeError> {
+    ) -> Result<TradeReceipt, TradeError> {
         let state = &mut self.state;
         let trade = state.trade(amount, self.key.fee, from0);
 
-        if let Some(result) = trade {
-            return Ok(crate::any_trade::UniTrade::V2(result));
+        let Some(result) = trade else {
+            return Err(TradeError::V2);
         };
 
-        Err(crate::err::TradeError::V2)
+        Ok(TradeReceipt {
+            fee: U24::from(self.key.fee),
+            fee_amount: result.fee_amount,
+            token0: self.key.token0,
+            token1: self.key.token1,
+            pool: *self.contract.address(),
+            pool_id: None,
+            from0,
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            price_before: self.get_price(),
+            price_after: Price::from_amounts(result.new_reserves0, result.new_reserves1)
+                .unwrap_or_default(),
+            ticks_crossed: 0,
+            synced_block: self.sync.last_synced_block,
+            checkpoint: self.checkpoint(),
+            trade: UniTrade::V2(result),
+        })
     }
 
     async fn sync(&mut self) -> Result<(), ()> {
@@ -88,6 +133,7 @@ impl<P: Provider> UniPool for V2Pool<P> {
             return Err(());
         }
 
+        self.sync.mark_synced(None);
         Ok(())
     }
 
@@ -111,6 +157,49 @@ impl<P: Provider> UniPool for V2Pool<P> {
             return Err(());
         }
 
+        self.sync.mark_synced(None);
+        Ok(())
+    }
+
+    fn apply_trade(&mut self, receipt: &TradeReceipt) -> Result<(), StateError> {
+        let UniTrade::V2(trade) = &receipt.trade else {
+            return Err(StateError::WrongPool);
+        };
+        if receipt.pool != *self.contract.address() {
+            return Err(StateError::WrongPool);
+        }
+        if receipt.checkpoint != self.checkpoint() {
+            return Err(StateError::Changed);
+        }
+        self.state.apply_trade(trade);
+        Ok(())
+    }
+
+    fn sync_status(&self) -> &SyncStatus {
+        &self.sync
+    }
+
+    fn sync_status_mut(&mut self) -> &mut SyncStatus {
+        &mut self.sync
+    }
+
+    fn checkpoint(&self) -> PoolCheckpoint {
+        PoolCheckpoint::V2 {
+            reserves0: self.state.reserves0,
+            reserves1: self.state.reserves1,
+        }
+    }
+
+    fn rollback(&mut self, checkpoint: PoolCheckpoint) -> Result<(), StateError> {
+        let PoolCheckpoint::V2 {
+            reserves0,
+            reserves1,
+        } = checkpoint
+        else {
+            return Err(StateError::WrongPool);
+        };
+        self.state.reserves0 = reserves0;
+        self.state.reserves1 = reserves1;
         Ok(())
     }
 
@@ -122,11 +211,8 @@ impl<P: Provider> UniPool for V2Pool<P> {
         &self.key.token1
     }
 
-    fn get_price(&self) -> U256 {
-        self.state
-            .reserves1
-            .checked_div(self.state.reserves0)
-            .unwrap_or_default()
+    fn get_price(&self) -> Price {
+        Price::from_amounts(self.state.reserves0, self.state.reserves1).unwrap_or_default()
     }
 
     fn get_liquidity(&self) -> U256 {
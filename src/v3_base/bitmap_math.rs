@@ -29,6 +29,15 @@ pub fn extract_ticks_from_bitmap(
     }
     ticks
 }
+/// First and last tick of the bitmap word `word_idx`
+pub fn word_range(word_idx: i16, tick_spacing: I24) -> Option<(I24, I24)> {
+    let first = I24::try_from(word_idx as i32 * 256).ok()?;
+    let last = I24::try_from(word_idx as i32 * 256 + 255).ok()?;
+    Some((
+        first.checked_mul(tick_spacing)?,
+        last.checked_mul(tick_spacing)?,
+    ))
+}
 pub fn get_pos_from_tick(tick: I24, tick_spacing: I24) -> i16 {
     let normalized_tick = normalize_tick(tick, tick_spacing);
     word_index(normalized_tick)
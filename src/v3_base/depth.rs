@@ -0,0 +1,217 @@
+use alloy::primitives::{U256, U512};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    price::Price,
+    v3_base::{
+        tick_math::price_from_tick,
+        v3_state::V3State,
+        x96price_math::{compute_amount_possible, update_liquidity},
+    },
+};
+
+/// Price moves of the default depth ladder in basis points
+pub const DEFAULT_DEPTH_BPS: [u32; 6] = [
+    10, 50, 100, 200, 500, 1000,
+];
+
+/// Amounts that move a pool from its price to `x96price`, before fees. Moving the
+/// price down takes token0 in, moving it up takes token1 in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Depth {
+    pub from0: bool,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub x96price: U256,
+    pub ticks_crossed: u32,
+}
+
+/// Depth on both sides of the price for a move of `bps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DepthLevel {
+    pub bps: u32,
+    pub down: Depth,
+    pub up: Depth,
+}
+
+/// sqrtPriceX96 after moving the price of `x96price` by `bps`, negative moves it
+/// down. `None` for moves of -100% or more.
+pub fn target_price(x96price: U256, bps: i32) -> Option<U256> {
+    let factor = 10_000i64.checked_add(bps as i64)?;
+    if factor <= 0 {
+        return None;
+    }
+    let price = Price::from_sqrt_x96(x96price);
+    let target = Price {
+        x192: price.x192.checked_mul(U512::from(factor))? / U512::from(10_000),
+    };
+    Some(target.to_sqrt_x96())
+}
+
+/// Walks the initialized ticks from the pool price to `target`, summing what the
+/// pool takes in and pays out. `None` when `target` lies outside the synced tick
+/// range or a tick on the way has an unknown `liquidity_net`.
+pub fn amount_to_price(state: &V3State, target: U256) -> Option<Depth> {
+    let from0 = target < state.x96price;
+
+    // past the synced range an unloaded tick may change the liquidity
+    let (lower, upper) = state.ticks.synced_range()?;
+    let edge = if from0 {
+        price_from_tick(lower)
+    } else {
+        price_from_tick(upper)
+    };
+    // an edge beyond the tick math range bounds nothing
+    if edge.is_some_and(|edge| (from0 && target < edge) || (!from0 && target > edge)) {
+        return None;
+    }
+    let mut depth = Depth {
+        from0,
+        x96price: state.x96price,
+        ..Default::default()
+    };
+    let mut liquidity = state.liquidity;
+
+    // selling token0 crosses the tick the price sits on first, buying it the next one
+    let mut next = match state.ticks.get_tick_index(state.tick) {
+        Ok(i) if from0 => Some(i),
+        Ok(i) => Some(i + 1),
+        Err(i) if from0 => i.checked_sub(1),
+        Err(i) => Some(i),
+    }
+    .filter(|i| *i < state.ticks.len());
+
+    while depth.x96price != target {
+        let tick = next.and_then(|i| state.ticks.get(i)).copied();
+        let tick_price = match tick {
+            Some(t) => price_from_tick(t.tick)?,
+            None => target,
+        };
+        let step_price = if from0 {
+            tick_price.max(target)
+        } else {
+            tick_price.min(target)
+        };
+
+        if step_price != depth.x96price {
+            let (low, high) = if from0 {
+                (step_price, depth.x96price)
+            } else {
+                (depth.x96price, step_price)
+            };
+            let amount0 = compute_amount_possible(true, &liquidity, &high, &low)?;
+            let amount1 = compute_amount_possible(false, &liquidity, &low, &high)?;
+            let (amount_in, amount_out) = if from0 {
+                (amount0, amount1)
+            } else {
+                (amount1, amount0)
+            };
+            depth.amount_in = depth.amount_in.checked_add(amount_in)?;
+            depth.amount_out = depth.amount_out.checked_add(amount_out)?;
+            depth.x96price = step_price;
+        }
+
+        let Some(tick) = tick.filter(|_| step_price == tick_price) else {
+            break;
+        };
+        // crossing a tick upwards adds its net liquidity, downwards removes it
+        let net = tick.liquidity_net?;
+        let net = if from0 {
+            net.checked_neg()?
+        } else {
+            net
+        };
+        liquidity = update_liquidity(liquidity, net)?;
+        depth.ticks_crossed += 1;
+        next = next.and_then(|i| {
+            if from0 {
+                i.checked_sub(1)
+            } else {
+                Some(i + 1)
+            }
+        });
+    }
+
+    Some(depth)
+}
+
+/// Amounts that move the pool price by `bps`, negative moves it down
+pub fn amount_to_move(state: &V3State, bps: i32) -> Option<Depth> {
+    amount_to_price(state, target_price(state.x96price, bps)?)
+}
+
+/// Cumulative depth at every move in `levels`, e.g. `DEFAULT_DEPTH_BPS`
+pub fn depth_ladder(state: &V3State, levels: &[u32]) -> Option<Vec<DepthLevel>> {
+    levels
+        .iter()
+        .map(|bps| {
+            let signed = i32::try_from(*bps).ok()?;
+            Some(DepthLevel {
+                bps: *bps,
+                down: amount_to_move(state, -signed)?,
+                up: amount_to_move(state, signed)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::aliases::{I24, U24};
+
+    use super::*;
+    use crate::v3_base::{ticks::Tick, trade_math};
+
+    #[test]
+    fn depth_matches_trade() {
+        let spacing = I24::try_from(10).unwrap();
+        let mut state = V3State::default(spacing);
+        state.x96price = U256::ONE << 96;
+        state.liquidity = U256::from(10u128.pow(21));
+        state.ticks.insert_ticks(vec![
+            Tick {
+                tick: I24::try_from(-50).unwrap(),
+                liquidity_net: Some(10i128.pow(21)),
+            },
+            Tick {
+                tick: I24::try_from(50).unwrap(),
+                liquidity_net: Some(-(10i128.pow(21))),
+            },
+        ]);
+        assert!(amount_to_move(&state, -20).is_none());
+        state.ticks.set_synced_range(
+            I24::try_from(-1500).unwrap(),
+            I24::try_from(1500).unwrap(),
+        );
+
+        let inside = amount_to_move(&state, -20).unwrap();
+        assert!(inside.from0);
+        assert_eq!(inside.ticks_crossed, 0);
+
+        let trade =
+            trade_math::trade(&state, &U24::ZERO, inside.amount_in, true).unwrap();
+        let diff = trade.amount_out.abs_diff(inside.amount_out);
+        assert!(
+            diff <= U256::from(2),
+            "{} vs {}",
+            trade.amount_out,
+            inside.amount_out
+        );
+
+        // no liquidity past the outer ticks, depth stops growing there
+        let ladder = depth_ladder(
+            &state,
+            &[
+                100, 200,
+            ],
+        )
+        .unwrap();
+        assert_eq!(ladder[0].up.ticks_crossed, 1);
+        assert_eq!(ladder[0].up.amount_in, ladder[1].up.amount_in);
+        assert_eq!(ladder[0].down.amount_out, ladder[1].down.amount_out);
+
+        // a 20% move leaves the synced ticks
+        assert!(amount_to_move(&state, 2_000).is_none());
+        assert!(depth_ladder(&state, &DEFAULT_DEPTH_BPS).is_some());
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use alloy::primitives::{aliases::I24, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    price::Price,
+    v3_base::{
+        tick_math::price_from_tick,
+        v3_state::V3State,
+        x96price_math::{compute_amount_possible, update_liquidity},
+    },
+};
+
+/// Liquidity active between two neighbouring initialized ticks and the tokens it
+/// holds at the current price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquidityRange {
+    pub tick_lower: I24,
+    pub tick_upper: I24,
+    pub active_liquidity: U256,
+    pub price_lower: Price,
+    pub price_upper: Price,
+    pub amount0: U256,
+    pub amount1: U256,
+}
+
+/// Ranges of a tick set sorted by `tick_lower`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquidityDistribution {
+    pub ranges: Vec<LiquidityRange>,
+}
+
+impl LiquidityDistribution {
+    /// Walks the initialized ticks up and down from the current tick, accumulating
+    /// `liquidity_net`. A direction stops at the first tick that was not synced.
+    pub fn from_state(state: &V3State) -> Option<Self> {
+        let ticks = &state.ticks;
+        // index of the initialized tick at or below the current tick
+        let lower = match ticks.get_tick_index(state.tick) {
+            Ok(i) => Some(i),
+            Err(i) => i.checked_sub(1),
+        };
+        let mut ranges = Vec::new();
+
+        let mut liquidity = state.liquidity;
+        let mut i = lower.unwrap_or(0);
+        if lower.is_none() && ticks.len() > 0 {
+            // below every initialized tick, the first one opens the first range
+            liquidity = update_liquidity(liquidity, ticks.get(0)?.liquidity_net?)?;
+        }
+        while i + 1 < ticks.len() {
+            let (low, high) = (*ticks.get(i)?, *ticks.get(i + 1)?);
+            ranges.push(range(state, low.tick, high.tick, liquidity)?);
+            let Some(net) = high.liquidity_net else {
+                break;
+            };
+            liquidity = update_liquidity(liquidity, net)?;
+            i += 1;
+        }
+
+        let mut liquidity = state.liquidity;
+        let mut i = lower.unwrap_or(0);
+        while lower.is_some() && i > 0 {
+            let (low, high) = (*ticks.get(i - 1)?, *ticks.get(i)?);
+            // crossing a tick downwards removes its net liquidity
+            let Some(net) = high.liquidity_net else {
+                break;
+            };
+            liquidity = update_liquidity(liquidity, net.checked_neg()?)?;
+            ranges.push(range(state, low.tick, high.tick, liquidity)?);
+            i -= 1;
+        }
+
+        ranges.sort_by_key(|r| r.tick_lower);
+        Some(Self {
+            ranges,
+        })
+    }
+
+    /// One line per range with a header, prices as raw token1 per token0 ratios
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "tick_lower,tick_upper,active_liquidity,price_lower,price_upper,amount0,amount1\n",
+        );
+        for r in &self.ranges {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                r.tick_lower,
+                r.tick_upper,
+                r.active_liquidity,
+                r.price_lower.to_f64(),
+                r.price_upper.to_f64(),
+                r.amount0,
+                r.amount1
+            );
+        }
+        csv
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, data)
+    }
+}
+
+/// Tokens held by `liquidity` between `lower` and `upper`: token0 above the current
+/// price, token1 below it
+fn range(
+    state: &V3State,
+    lower: I24,
+    upper: I24,
+    liquidity: U256,
+) -> Option<LiquidityRange> {
+    let sqrt_lower = price_from_tick(lower)?;
+    let sqrt_upper = price_from_tick(upper)?;
+    let current = state.x96price.clamp(sqrt_lower, sqrt_upper);
+
+    let amount0 = if current < sqrt_upper {
+        compute_amount_possible(true, &liquidity, &sqrt_upper, &current)?
+    } else {
+        U256::ZERO
+    };
+    let amount1 = if current > sqrt_lower {
+        compute_amount_possible(false, &liquidity, &sqrt_lower, &current)?
+    } else {
+        U256::ZERO
+    };
+
+    Some(LiquidityRange {
+        tick_lower: lower,
+        tick_upper: upper,
+        active_liquidity: liquidity,
+        price_lower: Price::from_sqrt_x96(sqrt_lower),
+        price_upper: Price::from_sqrt_x96(sqrt_upper),
+        amount0,
+        amount1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v3_base::ticks::Tick;
+
+    const E18: i128 = 10i128.pow(18);
+
+    #[test]
+    fn overlapping_positions() {
+        let tick = |t: i32, net: i128| Tick {
+            tick: I24::try_from(t).unwrap(),
+            liquidity_net: Some(net * E18),
+        };
+        let mut state = V3State::default(I24::try_from(10).unwrap());
+        state.x96price = U256::ONE << 96;
+        state.liquidity = U256::from(300 * E18);
+        state.ticks.insert_ticks(vec![
+            tick(-100, 100),
+            tick(-50, 200),
+            tick(50, -200),
+            tick(100, -100),
+        ]);
+
+        let distribution = LiquidityDistribution::from_state(&state).unwrap();
+        let liquidity: Vec<U256> = distribution
+            .ranges
+            .iter()
+            .map(|r| r.active_liquidity)
+            .collect();
+        assert_eq!(liquidity, [100, 300, 100].map(|l| U256::from(l * E18)));
+
+        // the range holding the price holds both tokens, the others only one
+        let [below, active, above] = distribution.ranges[..] else {
+            panic!("three ranges");
+        };
+        assert!(below.amount0.is_zero() && !below.amount1.is_zero());
+        assert!(!active.amount0.is_zero() && !active.amount1.is_zero());
+        assert!(!above.amount0.is_zero() && above.amount1.is_zero());
+
+        let csv = distribution.to_csv();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with("-50,50,300000000000000000000,"));
+    }
+}
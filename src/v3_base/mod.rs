@@ -1,5 +1,8 @@
 pub mod bitmap;
 pub mod bitmap_math;
+pub mod depth;
+pub mod distribution;
+pub mod oracle;
 pub mod states;
 pub mod tick_math;
 pub mod ticks;
@@ -0,0 +1,332 @@
+use alloy::primitives::{aliases::I24, U160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::price::Price;
+
+/// One slot of the pool's `observations` array
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Observation {
+    pub block_timestamp: u32,
+    pub tick_cumulative: i64,
+    pub seconds_per_liquidity_cumulative_x128: U160,
+    pub initialized: bool,
+}
+
+impl Observation {
+    /// The observation `time` seconds later with `tick` and `liquidity` in effect
+    /// since this one, the pool's `Oracle.transform`
+    pub fn transform(&self, time: u32, tick: I24, liquidity: u128) -> Self {
+        let delta = time.wrapping_sub(self.block_timestamp);
+        let tick: i32 = tick.as_i32();
+        Self {
+            block_timestamp: time,
+            tick_cumulative: self
+                .tick_cumulative
+                .wrapping_add(tick as i64 * delta as i64),
+            seconds_per_liquidity_cumulative_x128: self
+                .seconds_per_liquidity_cumulative_x128
+                .wrapping_add(U160::wrapping_from(
+                    (U256::from(delta) << 128) / U256::from(liquidity.max(1)),
+                )),
+            initialized: true,
+        }
+    }
+}
+
+/// Time weighted averages over `seconds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Twap {
+    pub seconds: u32,
+    /// Arithmetic mean tick, rounded towards negative infinity
+    pub tick: I24,
+    pub harmonic_mean_liquidity: u128,
+}
+
+impl Twap {
+    /// Computes the averages from the cumulatives of `observe([seconds, 0])`, as
+    /// `OracleLibrary.consult` does. `None` for a zero window.
+    pub fn from_cumulatives(
+        seconds: u32,
+        tick_cumulatives: [i64; 2],
+        seconds_per_liquidity: [U160; 2],
+    ) -> Option<Self> {
+        if seconds == 0 {
+            return None;
+        }
+        let delta = tick_cumulatives[1].checked_sub(tick_cumulatives[0])?;
+        let mut tick = delta / seconds as i64;
+        if delta < 0 && delta % seconds as i64 != 0 {
+            tick -= 1;
+        }
+
+        let spl_delta = seconds_per_liquidity[1].wrapping_sub(seconds_per_liquidity[0]);
+        let denominator: U256 = U256::from(spl_delta) << 32;
+        if denominator.is_zero() {
+            return None;
+        }
+        let seconds_x160 = U256::from(seconds) * U256::from(U160::MAX);
+
+        Some(Self {
+            seconds,
+            tick: I24::try_from(tick).ok()?,
+            harmonic_mean_liquidity: (seconds_x160 / denominator).saturating_to(),
+        })
+    }
+
+    pub fn price(&self) -> Option<Price> {
+        Price::from_tick(self.tick)
+    }
+
+    /// Distance from `tick` in ticks, one tick is about one basis point of price
+    pub fn deviation(&self, tick: I24) -> u32 {
+        (self.tick.as_i32() - tick.as_i32()).unsigned_abs()
+    }
+}
+
+/// Buffer position a `write` replaced, to take it back on a reorg
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservationUndo {
+    index: u16,
+    cardinality: u16,
+    len: usize,
+    replaced: Option<Observation>,
+}
+
+/// Local copy of a pool's observation ring buffer
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservationBuffer {
+    pub observations: Vec<Observation>,
+    pub index: u16,
+    pub cardinality: u16,
+    pub cardinality_next: u16,
+}
+
+impl ObservationBuffer {
+    /// A buffer seeded with one observation at `time`, like `initialize` on a new pool
+    pub fn new(time: u32) -> Self {
+        Self {
+            observations: vec![
+                Observation {
+                    block_timestamp: time,
+                    initialized: true,
+                    ..Default::default()
+                },
+            ],
+            index: 0,
+            cardinality: 1,
+            cardinality_next: 1,
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Observation> {
+        self.observations.get(self.index as usize)
+    }
+
+    /// Records the first swap of a block at `time`. `tick` and `liquidity` are the
+    /// values before the swap, as the pool writes them. Later swaps of the same
+    /// block don't write and return `None`.
+    pub fn write(
+        &mut self,
+        time: u32,
+        tick: I24,
+        liquidity: u128,
+    ) -> Option<ObservationUndo> {
+        let last = self.latest().copied()?;
+        if last.block_timestamp == time {
+            return None;
+        }
+        let mut undo = ObservationUndo {
+            index: self.index,
+            cardinality: self.cardinality,
+            len: self.observations.len(),
+            replaced: None,
+        };
+
+        if self.cardinality_next > self.cardinality && self.index == self.cardinality - 1
+        {
+            self.cardinality = self.cardinality_next;
+        }
+        self.index = ((self.index as u32 + 1) % self.cardinality as u32) as u16;
+
+        let index = self.index as usize;
+        if self.observations.len() <= index {
+            self.observations
+                .resize(index + 1, Observation::default());
+        } else {
+            undo.replaced = Some(self.observations[index]);
+        }
+        self.observations[index] = last.transform(time, tick, liquidity);
+        Some(undo)
+    }
+
+    /// Takes back the `write` that returned `undo`, writes after it first
+    pub fn undo(&mut self, undo: ObservationUndo) {
+        let index = self.index as usize;
+        match undo.replaced {
+            Some(observation) => self.observations[index] = observation,
+            None => self.observations.truncate(undo.len),
+        }
+        self.index = undo.index;
+        self.cardinality = undo.cardinality;
+    }
+
+    /// Cumulatives `seconds_ago` before `time`, interpolated between observations
+    /// like `observeSingle`. `tick` and `liquidity` are the current values. `None`
+    /// when the target is older than the buffer.
+    pub fn observe_single(
+        &self,
+        time: u32,
+        seconds_ago: u32,
+        tick: I24,
+        liquidity: u128,
+    ) -> Option<Observation> {
+        let target = time.checked_sub(seconds_ago)?;
+        let last = *self.latest()?;
+        if last.block_timestamp <= target {
+            if last.block_timestamp == target {
+                return Some(last);
+            }
+            return Some(last.transform(target, tick, liquidity));
+        }
+
+        let (before, after) = self.surrounding(target)?;
+        if before.block_timestamp == target {
+            return Some(before);
+        }
+        if after.block_timestamp == target {
+            return Some(after);
+        }
+
+        let span = after.block_timestamp - before.block_timestamp;
+        let elapsed = target - before.block_timestamp;
+        let tick_delta = (after.tick_cumulative - before.tick_cumulative) / span as i64;
+        let spl_delta = after
+            .seconds_per_liquidity_cumulative_x128
+            .wrapping_sub(before.seconds_per_liquidity_cumulative_x128);
+
+        Some(Observation {
+            block_timestamp: target,
+            tick_cumulative: before.tick_cumulative + tick_delta * elapsed as i64,
+            seconds_per_liquidity_cumulative_x128: before
+                .seconds_per_liquidity_cumulative_x128
+                .wrapping_add(U160::wrapping_from(
+                    U256::from(spl_delta) * U256::from(elapsed) / U256::from(span),
+                )),
+            initialized: true,
+        })
+    }
+
+    /// Averages over the last `seconds` before `time`
+    pub fn twap(
+        &self,
+        time: u32,
+        seconds: u32,
+        tick: I24,
+        liquidity: u128,
+    ) -> Option<Twap> {
+        let start = self.observe_single(time, seconds, tick, liquidity)?;
+        let end = self.observe_single(time, 0, tick, liquidity)?;
+        Twap::from_cumulatives(
+            seconds,
+            [
+                start.tick_cumulative,
+                end.tick_cumulative,
+            ],
+            [
+                start.seconds_per_liquidity_cumulative_x128,
+                end.seconds_per_liquidity_cumulative_x128,
+            ],
+        )
+    }
+
+    /// Initialized observations at or before and at or after `target`, found by
+    /// binary search over the ring from the oldest entry
+    fn surrounding(&self, target: u32) -> Option<(Observation, Observation)> {
+        let cardinality = self.cardinality as usize;
+        if cardinality == 0 {
+            return None;
+        }
+        let get = |i: usize| {
+            self.observations
+                .get(i % cardinality)
+                .copied()
+                .unwrap_or_default()
+        };
+
+        let oldest_index = (self.index as usize + 1) % cardinality;
+        let oldest = match get(oldest_index) {
+            o if o.initialized => o,
+            _ => get(0),
+        };
+        if target < oldest.block_timestamp {
+            return None;
+        }
+
+        let mut low = oldest_index;
+        let mut high = low + cardinality - 1;
+        while low <= high {
+            let i = (low + high) / 2;
+            let before = get(i);
+            if !before.initialized {
+                low = i + 1;
+                continue;
+            }
+            let after = get(i + 1);
+            if before.block_timestamp <= target && target <= after.block_timestamp {
+                return Some((before, after));
+            }
+            if before.block_timestamp < target {
+                low = i + 1;
+            } else {
+                high = i.checked_sub(1)?;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer_twap() {
+        let tick = |t: i32| I24::try_from(t).unwrap();
+        let mut buffer = ObservationBuffer::new(1_000);
+        buffer.cardinality_next = 4;
+
+        // tick 100 for 60s, then -20 for 40s
+        buffer.write(1_060, tick(100), 5_000);
+        buffer.write(1_100, tick(-20), 5_000);
+        assert_eq!(buffer.cardinality, 4);
+        assert_eq!(buffer.index, 2);
+
+        let twap = buffer.twap(1_100, 100, tick(-20), 5_000).unwrap();
+        assert_eq!(twap.tick, tick(52));
+        assert_eq!(twap.harmonic_mean_liquidity, 5_000);
+
+        // half of the window sits between the first two observations
+        let recent = buffer.twap(1_120, 50, tick(-20), 5_000).unwrap();
+        assert_eq!(recent.tick, tick(-20));
+        assert_eq!(buffer.twap(1_100, 101, tick(-20), 5_000), None);
+
+        let before = buffer.clone();
+        let undo = buffer.write(1_130, tick(7), 5_000).unwrap();
+        assert_eq!(buffer.write(1_130, tick(8), 5_000), None);
+        buffer.undo(undo);
+        assert_eq!(buffer, before);
+
+        // a negative mean rounds down
+        let negative = Twap::from_cumulatives(
+            3,
+            [
+                0, -4,
+            ],
+            [
+                U160::ZERO,
+                U160::from(1u64 << 40),
+            ],
+        );
+        assert_eq!(negative.unwrap().tick, tick(-2));
+    }
+}
@@ -1,20 +1,38 @@
 use alloy::primitives::{
     aliases::{I24, U24},
-    Address, U256,
+    Address, B256, U256,
 };
 
-use crate::v3_base::ticks::Tick;
+use crate::{
+    any_trade::UniTrade,
+    pool::PoolCheckpoint,
+    price::Price,
+    v3_base::ticks::Tick,
+};
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+/// Result of a simulated swap on any pool kind
+#[derive(Clone, Debug)]
 pub struct TradeReceipt {
     pub fee: U24,
     pub fee_amount: U256,
     pub token0: Address,
     pub token1: Address,
+    /// Pool contract, the state view for V4 pools
     pub pool: Address,
+    /// V4 pool id
+    pub pool_id: Option<B256>,
     pub from0: bool,
     pub amount_in: U256,
     pub amount_out: U256,
+    pub price_before: Price,
+    pub price_after: Price,
+    pub ticks_crossed: u32,
+    /// Block the pool state was last synced at, when known
+    pub synced_block: Option<u64>,
+    /// Pool state the trade was quoted on
+    pub checkpoint: PoolCheckpoint,
+    /// Protocol specific state after the swap
+    pub trade: UniTrade,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -27,6 +45,7 @@ pub struct TradeState {
     pub tick: I24,
     pub remaining: U256,
     pub from0: bool,
+    pub ticks_crossed: u32,
     pub step: TradeStep,
 }
 #[derive(Debug, Clone, Copy, Default)]
@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 )]
 pub struct Ticks {
     ticks: Vec<Tick>,
+    /// Inclusive tick range every initialized tick was fetched for
+    #[serde(default)]
+    synced: Option<(I24, I24)>,
 }
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize,
@@ -22,6 +25,7 @@ impl Ticks {
 
         Ticks {
             ticks,
+            synced: None,
         }
     }
 
@@ -53,10 +57,10 @@ impl Ticks {
             let stick = self.ticks[self_idx].tick;
             let ntick = ticks[new_idx].tick;
 
-            if stick > ntick {
+            if stick < ntick {
                 all_ticks.push(self.ticks[self_idx]);
                 self_idx += 1;
-            } else if stick < ntick {
+            } else if stick > ntick {
                 all_ticks.push(ticks[new_idx]);
                 new_idx += 1;
             } else {
@@ -73,6 +77,32 @@ impl Ticks {
         self.ticks = all_ticks;
     }
 
+    pub fn remove_tick(&mut self, tick: I24) {
+        if let Ok(idx) = self.get_tick_index(tick) {
+            self.ticks.remove(idx);
+        }
+    }
+
+    /// Extends the synced range to `lower..=upper`, a disjoint range replaces it
+    pub fn set_synced_range(&mut self, lower: I24, upper: I24) {
+        self.synced = match self.synced {
+            Some((l, u)) if lower <= u && l <= upper => {
+                Some((l.min(lower), u.max(upper)))
+            }
+            _ => Some((lower, upper)),
+        };
+    }
+
+    pub fn synced_range(&self) -> Option<(I24, I24)> {
+        self.synced
+    }
+
+    /// Whether a tick missing from the list is known to be uninitialized
+    pub fn is_synced(&self, tick: I24) -> bool {
+        self.synced
+            .is_some_and(|(lower, upper)| lower <= tick && tick <= upper)
+    }
+
     pub fn len(&self) -> usize {
         self.ticks.len()
     }
@@ -1,4 +1,7 @@
-use alloy::primitives::{aliases::U24, U256, U512};
+use alloy::primitives::{
+    aliases::{I24, U24},
+    U256, U512,
+};
 
 use crate::{
     err::{MathError, TickError, TradeError},
@@ -9,12 +12,12 @@ use crate::{
         v3_state::V3State,
         x96price_math::{
             compute_amount_possible, compute_price_from0, compute_price_from1,
+            update_liquidity,
         },
     },
 };
 
 pub fn retry(trade_state: TradeState, ticks: &Ticks) -> Result<TradeState, TradeError> {
-    println!("retrying trade");
     trade_loop(trade_state, ticks)
 }
 
@@ -25,10 +28,7 @@ pub fn trade(
     from0: bool,
 ) -> Result<TradeState, TradeError> {
     let trade_state = trade_start(pool, fee, amount_in, from0)?;
-    trade_loop(trade_state, &pool.ticks)?;
-
-    // build Trade
-    Ok(trade_state)
+    trade_loop(trade_state, &pool.ticks)
 }
 //////////////////////////////
 pub fn trade_start(
@@ -46,6 +46,7 @@ pub fn trade_start(
         amount_in,
         tick: pool.tick,
         from0,
+        ticks_crossed: 0,
         step: TradeStep::default(),
     };
     let fee_amount = amount_in
@@ -61,40 +62,33 @@ pub fn trade_start(
     trade_state.x96price = pool.x96price;
     trade_state.tick = tick_from_price(pool.x96price).ok_or(MathError::A(trade_state))?;
     trade_state.liquidity = pool.liquidity;
-    match pool.ticks.get_tick(trade_state.tick) {
-        Ok(_) => return Ok(trade_state),
-        Err(_) => {
-            trade_state.step.next_tick.tick = trade_state.tick; //just to work with the error
-            return Err(TickError::Overflow(trade_state).into());
-        }
-    }
+
+    Ok(trade_state)
 }
 pub fn step_start(trade_state: &mut TradeState, ticks: &Ticks) -> Result<(), TradeError> {
+    // selling token0 moves the price down, selling token1 moves it up
     trade_state.step.next_tick_index = match ticks.get_tick_index(trade_state.tick) {
         Ok(i) => {
             if trade_state.from0 {
+                i
+            } else {
                 if i + 1 >= ticks.len() {
                     return Err(TickError::Overflow(*trade_state).into());
                 } // No ticks above
                 i + 1
-            } else {
-                if i == 0 {
-                    return Err(TickError::Underflow(*trade_state).into());
-                } // No ticks below
-                i - 1
             }
         }
         Err(i) => {
             if trade_state.from0 {
-                if i >= ticks.len() {
-                    return Err(TickError::Overflow(*trade_state).into());
-                } // No ticks above
-                i
-            } else {
                 if i == 0 {
                     return Err(TickError::Underflow(*trade_state).into());
                 } // No ticks below
                 i - 1
+            } else {
+                if i >= ticks.len() {
+                    return Err(TickError::Overflow(*trade_state).into());
+                } // No ticks above
+                i
             }
         }
     };
@@ -110,6 +104,12 @@ pub fn step_start(trade_state: &mut TradeState, ticks: &Ticks) -> Result<(), Tra
     trade_state.step.next_price = price_from_tick(trade_state.step.next_tick.tick)
         .ok_or(MathError::A(*trade_state))?;
 
+    // the price sits exactly on the tick, it is crossed without moving the price
+    if trade_state.step.next_price == trade_state.x96price {
+        trade_state.step.amount_possible = U256::ZERO;
+        return Ok(());
+    }
+
     // compute max amount possible to cross this tick
     trade_state.step.amount_possible = compute_amount_possible(
         trade_state.from0,
@@ -125,34 +125,29 @@ pub fn step_start(trade_state: &mut TradeState, ticks: &Ticks) -> Result<(), Tra
 ////////////////////////////////////
 pub fn get_crossing_delta(trade_state: &mut TradeState) -> Result<(), TradeError> {
     // cross entire tick
-    if trade_state.from0 {
-        trade_state.step.delta = trade_state
-            .liquidity
-            .checked_mul(
-                trade_state
-                    .step
-                    .next_price
-                    .checked_sub(trade_state.x96price)
-                    .ok_or(MathError::A(*trade_state))?,
-            )
+    let q96 = U512::ONE << 96;
+    let liquidity = U512::from(trade_state.liquidity);
+    let cur = U512::from(trade_state.x96price);
+    let nxt = U512::from(trade_state.step.next_price);
+
+    let delta = if trade_state.from0 {
+        // Δy = L·(√P_curr − √P_next) ÷ Q96
+        liquidity
+            .checked_mul(cur.checked_sub(nxt).ok_or(MathError::A(*trade_state))?)
+            .ok_or(MathError::A(*trade_state))?
+            .checked_div(q96)
             .ok_or(MathError::A(*trade_state))?
-            .checked_div(U256::from(1u128 << 96))
-            .ok_or(MathError::A(*trade_state))?;
     } else {
-        let num = trade_state
-            .liquidity
-            .checked_mul(
-                trade_state
-                    .x96price
-                    .checked_sub(trade_state.step.next_price)
-                    .ok_or(MathError::A(*trade_state))?,
-            )
-            .ok_or(MathError::A(*trade_state))?;
-
-        trade_state.step.delta = num
-            .checked_div(U256::from(1u128 << 96))
-            .ok_or(MathError::A(*trade_state))?;
-    }
+        // Δx = L·(√P_next − √P_curr)·Q96 ÷ (√P_curr·√P_next)
+        liquidity
+            .checked_mul(nxt.checked_sub(cur).ok_or(MathError::A(*trade_state))?)
+            .ok_or(MathError::A(*trade_state))?
+            .checked_mul(q96)
+            .ok_or(MathError::A(*trade_state))?
+            .checked_div(cur.checked_mul(nxt).ok_or(MathError::A(*trade_state))?)
+            .ok_or(MathError::A(*trade_state))?
+    };
+    trade_state.step.delta = U256::from(delta);
     Ok(())
 }
 pub fn trade_loop(
@@ -161,16 +156,12 @@ pub fn trade_loop(
 ) -> Result<TradeState, TradeError> {
     while trade_state.remaining > U256::ZERO {
         step_start(&mut trade_state, ticks)?;
-        println!("hi");
         if trade_state.remaining < trade_state.step.amount_possible {
-            println!("not enough to cross finishing it");
             handle_non_crossing_step(&mut trade_state)?;
             break;
         }
 
-        println!("gettiing delta to cross");
         get_crossing_delta(&mut trade_state)?;
-        println!("prepping next step");
         update_state_for_next_step(&mut trade_state)?;
     }
     Ok(trade_state)
@@ -183,31 +174,23 @@ pub fn update_state_for_next_step(
         .amount_out
         .checked_add(trade_state.step.delta)
         .ok_or(MathError::A(*trade_state))?;
-    // update liquidity
+    // crossing a tick upwards adds its net liquidity, downwards removes it
     if let Some(net) = trade_state.step.next_tick.liquidity_net {
-        trade_state.liquidity = if trade_state.from0 {
-            if net > 0 {
-                trade_state
-                    .liquidity
-                    .saturating_add(U256::from(net))
-            } else {
-                trade_state
-                    .liquidity
-                    .saturating_sub(U256::from(-net))
-            }
+        let net = if trade_state.from0 {
+            net.checked_neg().ok_or(MathError::A(*trade_state))?
         } else {
-            if net < 0 {
-                trade_state
-                    .liquidity
-                    .saturating_add(U256::from(net))
-            } else {
-                trade_state
-                    .liquidity
-                    .saturating_sub(U256::from(net))
-            }
+            net
         };
+        trade_state.liquidity = update_liquidity(trade_state.liquidity, net)
+            .ok_or(MathError::A(*trade_state))?;
     }
-    trade_state.tick = trade_state.step.next_tick.tick;
+    // below an initialized tick the current tick is the one under it
+    trade_state.tick = if trade_state.from0 {
+        trade_state.step.next_tick.tick - I24::ONE
+    } else {
+        trade_state.step.next_tick.tick
+    };
+    trade_state.ticks_crossed += 1;
     trade_state.x96price = trade_state.step.next_price;
     trade_state.remaining = trade_state
         .remaining
@@ -236,8 +219,6 @@ pub fn handle_non_crossing_step(trade_state: &mut TradeState) -> Result<(), Trad
         )
         .ok_or(MathError::A(*trade_state))?
     };
-    println!("ah");
-
     let u512_curr_price = U512::from(trade_state.x96price);
     let u512_curr_liq = U512::from(trade_state.liquidity);
     // compute out
@@ -245,7 +226,6 @@ pub fn handle_non_crossing_step(trade_state: &mut TradeState) -> Result<(), Trad
         let price_diff = u512_curr_price
             .checked_sub(U512::from(new_price))
             .ok_or(MathError::A(*trade_state))?;
-        println!("diff {}", price_diff);
         u512_curr_liq
             .checked_mul(price_diff)
             .ok_or(MathError::A(*trade_state))?
@@ -274,15 +254,86 @@ pub fn handle_non_crossing_step(trade_state: &mut TradeState) -> Result<(), Trad
     };
     trade_state.step.delta = U256::from(delta);
 
-    println!("{}", trade_state.step.delta);
-
-    println!("be");
     trade_state.amount_out = trade_state
         .amount_out
         .checked_add(U256::from(delta))
         .ok_or(MathError::A(*trade_state))?;
     trade_state.remaining = U256::ZERO;
     trade_state.x96price = U256::from(new_price);
+    trade_state.tick =
+        tick_from_price(trade_state.x96price).ok_or(MathError::A(*trade_state))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v3_base::ticks::Tick;
+
+    const E21: i128 = 10i128.pow(21);
+
+    fn tick(t: i32) -> I24 {
+        I24::try_from(t).unwrap()
+    }
+
+    /// 2e21 of liquidity around price 1, half of it ending at ±60 and the rest at ±120
+    fn state() -> V3State {
+        let mut state = V3State::default(tick(60));
+        state.x96price = U256::ONE << 96;
+        state.liquidity = U256::from(2 * E21);
+        state.ticks.insert_ticks(
+            [(-120, E21), (-60, E21), (60, -E21), (120, -E21)]
+                .map(|(t, net)| Tick {
+                    tick: tick(t),
+                    liquidity_net: Some(net),
+                })
+                .to_vec(),
+        );
+        state
+    }
+
+    #[test]
+    fn both_directions_and_crossing() {
+        let state = state();
+        let fee = U24::from(3000);
+        let amount = U256::from(10u128.pow(18));
+
+        for from0 in [true, false] {
+            let result = trade(&state, &fee, amount, from0).unwrap();
+            assert_eq!(result.ticks_crossed, 0);
+            assert_eq!(result.fee_amount, amount * U256::from(3) / U256::from(1000));
+            // near price 1 the output is the input less fee and a little impact
+            assert!(result.amount_out < amount - result.fee_amount);
+            assert!(result.amount_out > amount * U256::from(99) / U256::from(100));
+            assert_eq!(result.x96price < state.x96price, from0);
+        }
+
+        let down = trade(&state, &fee, U256::from(7 * 10u128.pow(18)), true).unwrap();
+        assert_eq!(down.ticks_crossed, 1);
+        assert_eq!(down.liquidity, U256::from(E21));
+        assert!(down.tick < tick(-60) && down.tick >= tick(-120));
+
+        let up = trade(&state, &fee, U256::from(7 * 10u128.pow(18)), false).unwrap();
+        assert_eq!(up.ticks_crossed, 1);
+        assert_eq!(up.liquidity, U256::from(E21));
+        assert!(up.tick >= tick(60) && up.tick < tick(120));
+
+        // past the last synced tick there is nothing left to trade against
+        let too_big = trade(&state, &fee, U256::from(10u128.pow(22)), true);
+        assert!(matches!(too_big, Err(TradeError::Tick(TickError::Underflow(_)))));
+    }
+
+    #[test]
+    fn price_on_an_initialized_tick() {
+        let mut state = state();
+        state.x96price = price_from_tick(tick(-60)).unwrap();
+        state.tick = tick(-60);
+
+        let result = trade(&state, &U24::ZERO, U256::from(10u128.pow(17)), true).unwrap();
+        assert_eq!(result.ticks_crossed, 1);
+        assert_eq!(result.liquidity, U256::from(E21));
+        assert!(result.x96price < state.x96price);
+        assert!(!result.amount_out.is_zero());
+    }
+}
@@ -1,8 +1,14 @@
 use alloy::primitives::{aliases::I24, ruint::aliases::U256};
 use serde::{Deserialize, Serialize};
 
+use crate::{err::StateError, pool::PoolCheckpoint};
 
-use super::{bitmap::BitMap, ticks::Ticks};
+use super::{
+    bitmap::BitMap,
+    states::TradeState,
+    ticks::{Tick, Ticks},
+    x96price_math::update_liquidity,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct V3State {
@@ -28,4 +34,62 @@ impl V3State {
             x96price,
         }
     }
+
+    /// Writes the price, tick and active liquidity a trade ends with
+    pub fn apply_trade(&mut self, trade: &TradeState) {
+        self.x96price = trade.x96price;
+        self.tick = trade.tick;
+        self.liquidity = trade.liquidity;
+    }
+
+    /// Applies a position change between `lower` and `upper` as emitted by `Mint`,
+    /// `Burn` and `ModifyLiquidity`. A tick missing inside the synced range starts
+    /// from zero, one outside of it is added with an unknown `liquidity_net`.
+    pub fn modify_liquidity(&mut self, lower: I24, upper: I24, delta: i128) -> Option<()> {
+        for (tick, net) in [
+            (lower, delta),
+            (upper, delta.checked_neg()?),
+        ] {
+            let current = match self.ticks.get_tick(tick) {
+                Ok(t) => t.liquidity_net,
+                Err(_) if self.ticks.is_synced(tick) => Some(0),
+                Err(_) => None,
+            };
+            let liquidity_net = match current {
+                Some(current) => Some(current.checked_add(net)?),
+                None => None,
+            };
+            self.ticks.insert_ticks(vec![Tick {
+                tick,
+                liquidity_net,
+            }]);
+        }
+        if lower <= self.tick && self.tick < upper {
+            self.liquidity = update_liquidity(self.liquidity, delta)?;
+        }
+        Some(())
+    }
+
+    pub fn checkpoint(&self) -> PoolCheckpoint {
+        PoolCheckpoint::Concentrated {
+            tick: self.tick,
+            liquidity: self.liquidity,
+            x96price: self.x96price,
+        }
+    }
+
+    pub fn rollback(&mut self, checkpoint: PoolCheckpoint) -> Result<(), StateError> {
+        let PoolCheckpoint::Concentrated {
+            tick,
+            liquidity,
+            x96price,
+        } = checkpoint
+        else {
+            return Err(StateError::WrongPool);
+        };
+        self.tick = tick;
+        self.liquidity = liquidity;
+        self.x96price = x96price;
+        Ok(())
+    }
 }
@@ -1,12 +1,13 @@
 use alloy::primitives::{U256, U512};
 
+/// Input needed to move the price from `current_sqrt_price` to `next_sqrt_price`.
+/// Selling token0 moves the price down, selling token1 moves it up.
 pub fn compute_amount_possible(
     from0: bool,
-        available_liquidity: &U256,
+    available_liquidity: &U256,
     current_sqrt_price: &U256,
     next_sqrt_price: &U256,
 ) -> Option<U256> {
-    println!("computing amount possible");
     // Q96 = 2^96
     let q96: U512 = U512::ONE << 96;
 
@@ -16,66 +17,27 @@ pub fn compute_amount_possible(
     let nxt: U512 = U512::from(*next_sqrt_price);
 
     if from0 {
-        // Δx = L·(√P_next − √P_curr)·Q96 ÷ (√P_curr·√P_next)
-        println!("from 0");
-        println!(
-            "next price {}",
-            next_sqrt_price
-        );
-        println!(
-            "curr price {}",
-            current_sqrt_price
-        );
-
-        let diff = nxt.checked_sub(cur)?;
-        println!("passed");
-
+        // Δx = L·(√P_curr − √P_next)·Q96 ÷ (√P_curr·√P_next)
+        let diff = cur.checked_sub(nxt)?;
         if diff.is_zero() {
-            println!("diff is zezo");
             return None;
         }
 
-        println!(
-            "q 96 {}",
-            q96
-        );
-        println!(
-            "available liquidity {}",
-            available_liquidity
-        );
-        println!(
-            "diff {}",
-            diff
-        );
         // numerator = L * diff * Q96
-        let impact = liq.checked_mul(diff)?;
-        let numerator: U512 =
-            U512::from(impact).checked_mul(q96)?;
+        let numerator = liq.checked_mul(diff)?.checked_mul(q96)?;
 
         // denominator = cur * nxt
         let denominator = cur.checked_mul(nxt)?;
 
-        let res =
-            U256::from(numerator.checked_div(denominator)?);
-
-        Some(res)
+        Some(U256::from(numerator.checked_div(denominator)?))
     } else {
-        // Δy = L·(√P_curr − √P_next) ÷ Q96
-        let diff = cur.checked_sub(nxt)?;
-        println!(
-            "diff {}",
-            diff
-        );
+        // Δy = L·(√P_next − √P_curr) ÷ Q96
+        let diff = nxt.checked_sub(cur)?;
         if diff.is_zero() {
-            println!("diff is zero");
             return None;
         }
 
         let numerator = liq.checked_mul(diff)?;
-        println!(
-            "numerator {}",
-            numerator
-        );
         Some(U256::from(numerator.checked_div(q96)?))
     }
 }
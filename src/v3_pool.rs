@@ -1,6 +1,7 @@
 use std::future::Future;
 
 use alloy::{
+    eips::BlockId,
     primitives::{aliases::I24, Address, U160, U256},
     rpc::types::{EthCallResponse, TransactionRequest},
 };
@@ -11,9 +12,14 @@ use tokio::try_join;
 use crate::{
     any_pool::{AnyPool, V4Key},
     any_trade::UniTrade,
-    pool::{ConcentratedLiquidity, UniPool},
-    sol_types::V3Pool::{liquidityCall, slot0Call, V3PoolInstance},
+    err::{StateError, TradeError},
+    multicall::{self, Multicall},
+    pool::{ConcentratedLiquidity, PoolCheckpoint, SyncStatus, UniPool},
+    price::Price,
+    sol_types::V3Pool::{liquidityCall, observationsCall, slot0Call, V3PoolInstance},
     v3_base::{
+        oracle::{Observation, ObservationBuffer, Twap},
+        states::TradeReceipt,
         ticks::{Tick, Ticks},
         v3_state::V3State,
     },
@@ -24,6 +30,7 @@ pub struct V3Pool<P: Provider> {
     pub state: V3State,
     pub factory: Address,
     pub contract: V3PoolInstance<P>,
+    pub sync: SyncStatus,
 }
 
 impl<P: Provider> V3Pool<P> {
@@ -45,6 +52,7 @@ impl<P: Provider> V3Pool<P> {
             state,
             factory,
             contract,
+            sync: SyncStatus::default(),
         };
 
         Ok(p)
@@ -86,6 +94,7 @@ impl<P: Provider> V3Pool<P> {
             factory,
             state,
             contract,
+            sync: SyncStatus::default(),
         };
 
         if let Err(()) = p.sync().await {
@@ -98,6 +107,87 @@ impl<P: Provider> V3Pool<P> {
 
         Ok(p)
     }
+
+    /// Averages over the last `seconds` from the pool's own oracle,
+    /// `observe([seconds, 0])`
+    pub async fn twap(&self, seconds: u32) -> Result<Twap, ()> {
+        let observed = self
+            .contract
+            .observe(vec![
+                seconds, 0,
+            ])
+            .call()
+            .await
+            .map_err(|_| ())?;
+        let [t0, t1] = observed.tickCumulatives[..] else {
+            return Err(());
+        };
+        let [s0, s1] = observed.secondsPerLiquidityCumulativeX128s[..] else {
+            return Err(());
+        };
+        Twap::from_cumulatives(
+            seconds,
+            [
+                t0.as_i64(),
+                t1.as_i64(),
+            ],
+            [
+                s0, s1,
+            ],
+        )
+        .ok_or(())
+    }
+
+    /// Reads the whole observation ring through `multicall`, to keep it current
+    /// locally from swaps with `PoolRegistry::track_observations`. The ring and the
+    /// `slot0` index pointing into it are read at the same block.
+    pub async fn observation_buffer<M: Provider>(
+        &self,
+        multicall: &Multicall<M>,
+    ) -> Result<ObservationBuffer, ()> {
+        let block = self
+            .contract
+            .provider()
+            .get_block_number()
+            .await
+            .map_err(|_| ())?;
+        let block = BlockId::number(block);
+
+        let slot0 = self
+            .contract
+            .slot0()
+            .block(block)
+            .call()
+            .await
+            .map_err(|_| ())?;
+        let address = *self.contract.address();
+        let calls = (0..slot0.observationCardinality)
+            .map(|i| multicall::encode(address, &observationsCall::new((U256::from(i),))))
+            .collect();
+        let observations = multicall
+            .aggregate_at(calls, block)
+            .await
+            .map_err(|_| ())?
+            .iter()
+            .map(|bytes| {
+                let o = observationsCall::abi_decode_returns(bytes).map_err(|_| ())?;
+                Ok(Observation {
+                    block_timestamp: o.blockTimestamp,
+                    tick_cumulative: o.tickCumulative.as_i64(),
+                    seconds_per_liquidity_cumulative_x128: o
+                        .secondsPerLiquidityCumulativeX128,
+                    initialized: o.initialized,
+                })
+            })
+            .collect::<Result<Vec<_>, ()>>()?;
+
+        Ok(ObservationBuffer {
+            observations,
+            index: slot0.observationIndex,
+            cardinality: slot0.observationCardinality,
+            cardinality_next: slot0.observationCardinalityNext,
+        })
+    }
 }
 
 impl<P: Provider> UniPool for V3Pool<P> {
@@ -105,15 +195,31 @@ impl<P: Provider> UniPool for V3Pool<P> {
         &mut self,
         amount: alloy::primitives::U256,
         from0: bool,
-    ) -> Result<UniTrade, crate::err::TradeError> {
+    ) -> Result<TradeReceipt, TradeError> {
+        let checkpoint = self.state.checkpoint();
         let state = &mut self.state;
 
         let fee = self.key.fee;
 
-        match crate::v3_base::trade_math::trade_start(&state, &fee, amount, from0) {
-            Ok(ok) => Ok(UniTrade::V3(ok)),
-            Err(err) => Err(err),
-        }
+        let result = crate::v3_base::trade_math::trade(state, &fee, amount, from0)?;
+
+        Ok(TradeReceipt {
+            fee,
+            fee_amount: result.fee_amount,
+            token0: self.key.currency0,
+            token1: self.key.currency1,
+            pool: *self.contract.address(),
+            pool_id: None,
+            from0,
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            price_before: Price::from_sqrt_x96(state.x96price),
+            price_after: Price::from_sqrt_x96(result.x96price),
+            ticks_crossed: result.ticks_crossed,
+            synced_block: self.sync.last_synced_block,
+            checkpoint,
+            trade: UniTrade::V3(result),
+        })
     }
 
     async fn sync(&mut self) -> Result<(), ()> {
@@ -131,6 +237,7 @@ impl<P: Provider> UniPool for V3Pool<P> {
             state.tick = slot0.tick;
 
             if slot0.sqrtPriceX96 != U160::ZERO {
+                self.sync.mark_synced(None);
                 return Ok(());
             }
         }
@@ -184,9 +291,40 @@ impl<P: Provider> UniPool for V3Pool<P> {
         } else {
             return Err(());
         }
+        self.sync.mark_synced(None);
+        Ok(())
+    }
+
+    fn apply_trade(&mut self, receipt: &TradeReceipt) -> Result<(), StateError> {
+        let UniTrade::V3(trade) = &receipt.trade else {
+            return Err(StateError::WrongPool);
+        };
+        if receipt.pool != *self.contract.address() {
+            return Err(StateError::WrongPool);
+        }
+        if receipt.checkpoint != self.checkpoint() {
+            return Err(StateError::Changed);
+        }
+        self.state.apply_trade(trade);
         Ok(())
     }
 
+    fn sync_status(&self) -> &SyncStatus {
+        &self.sync
+    }
+
+    fn sync_status_mut(&mut self) -> &mut SyncStatus {
+        &mut self.sync
+    }
+
+    fn checkpoint(&self) -> PoolCheckpoint {
+        self.state.checkpoint()
+    }
+
+    fn rollback(&mut self, checkpoint: PoolCheckpoint) -> Result<(), StateError> {
+        self.state.rollback(checkpoint)
+    }
+
     fn get_a(&self) -> &alloy::primitives::Address {
         &self.key.currency0
     }
@@ -195,8 +333,8 @@ impl<P: Provider> UniPool for V3Pool<P> {
         &self.key.currency1
     }
 
-    fn get_price(&self) -> U256 {
-        self.state.x96price
+    fn get_price(&self) -> Price {
+        Price::from_sqrt_x96(self.state.x96price)
     }
 
     fn get_liquidity(&self) -> U256 {
@@ -255,3 +393,78 @@ impl<P: Provider> Into<AnyPool<P>> for V3Pool<P> {
         AnyPool::V3(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{
+        aliases::{I56, U24},
+        U64,
+    };
+
+    use super::*;
+    use crate::{
+        multicall::tests::{mocked, push_aggregate, push_call, ret},
+        sol_types::V3Pool::{observationsReturn, slot0Return},
+    };
+
+    #[tokio::test]
+    async fn observation_ring_matches_slot0() {
+        let (asserter, provider) = mocked();
+        let key = V4Key {
+            fee: U24::from(3000),
+            tickspacing: I24::try_from(60).unwrap(),
+            ..Default::default()
+        };
+        let pool = V3Pool::new_from_key(
+            Address::repeat_byte(1),
+            provider.clone(),
+            Address::ZERO,
+            key,
+        )
+        .unwrap();
+        let multicall = Multicall::new(provider);
+
+        let observation = |time: u32| observationsReturn {
+            blockTimestamp: time,
+            tickCumulative: I56::try_from(time * 10).unwrap(),
+            secondsPerLiquidityCumulativeX128: U160::from(time),
+            initialized: true,
+        };
+        asserter.push_success(&U64::from(100));
+        push_call::<slot0Call>(
+            &asserter,
+            &slot0Return {
+                sqrtPriceX96: U160::from(1) << 96,
+                tick: I24::ZERO,
+                observationIndex: 1,
+                observationCardinality: 2,
+                observationCardinalityNext: 4,
+                feeProtocol: 0,
+                unlocked: true,
+            },
+        );
+        push_aggregate(
+            &asserter,
+            vec![
+                ret::<observationsCall>(&observation(10)),
+                ret::<observationsCall>(&observation(20)),
+            ],
+        );
+
+        let buffer = pool.observation_buffer(&multicall).await.unwrap();
+        assert_eq!(
+            (buffer.index, buffer.cardinality, buffer.cardinality_next),
+            (1, 2, 4)
+        );
+        assert_eq!(buffer.observations.len(), 2);
+        assert_eq!(
+            buffer.observations[1],
+            Observation {
+                block_timestamp: 20,
+                tick_cumulative: 200,
+                seconds_per_liquidity_cumulative_x128: U160::from(20),
+                initialized: true,
+            }
+        );
+    }
+}
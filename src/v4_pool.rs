@@ -1,19 +1,20 @@
 use alloy::{
-    primitives::{aliases::I24, keccak256, B256, U160, U256},
+    primitives::{aliases::I24, B256, U160, U256},
     rpc::types::{EthCallResponse, TransactionRequest},
 };
 use alloy_provider::Provider;
-use alloy_sol_types::{SolCall, SolValue};
+use alloy_sol_types::SolCall;
 
 use crate::{
     any_pool::{AnyPool, V4Key},
     any_trade::UniTrade,
-    pool::{ConcentratedLiquidity, UniPool},
-    sol_types::{
-        PoolKey,
-        StateView::{getLiquidityCall, getSlot0Call, StateViewInstance},
-    },
+    err::{StateError, TradeError},
+    pool::{ConcentratedLiquidity, PoolCheckpoint, SyncStatus, UniPool},
+    pool_address::v4_pool_id,
+    price::Price,
+    sol_types::StateView::{getLiquidityCall, getSlot0Call, StateViewInstance},
     v3_base::{
+        states::TradeReceipt,
         ticks::{Tick, Ticks},
         v3_state::V3State,
     },
@@ -24,18 +25,19 @@ pub struct V4Pool<P: Provider> {
     pub id: B256,
     pub state: V3State,
     pub contract: StateViewInstance<P>,
+    pub sync: SyncStatus,
 }
 
 impl<P: Provider> V4Pool<P> {
     pub async fn new(key: V4Key, contract: StateViewInstance<P>) -> Result<Self, ()> {
         let state = V3State::default(key.tickspacing);
-        let id: PoolKey = key.into();
 
         let mut pool = Self {
             key,
-            id: keccak256(id.abi_encode()),
+            id: v4_pool_id(&key),
             state,
             contract,
+            sync: SyncStatus::default(),
         };
         println!("new v4 id: {}", pool.id);
 
@@ -60,14 +62,30 @@ impl<P: Provider> UniPool for V4Pool<P> {
         &mut self,
         amount: alloy::primitives::U256,
         from0: bool,
-    ) -> Result<UniTrade, crate::err::TradeError> {
+    ) -> Result<TradeReceipt, TradeError> {
+        let checkpoint = self.state.checkpoint();
         let state = &mut self.state;
         let fee = self.key.fee;
 
-        match crate::v3_base::trade_math::trade_start(&state, &fee, amount, from0) {
-            Ok(ok) => Ok(UniTrade::V3(ok)),
-            Err(err) => Err(err),
-        }
+        let result = crate::v3_base::trade_math::trade(state, &fee, amount, from0)?;
+
+        Ok(TradeReceipt {
+            fee,
+            fee_amount: result.fee_amount,
+            token0: self.key.currency0,
+            token1: self.key.currency1,
+            pool: *self.contract.address(),
+            pool_id: Some(self.id),
+            from0,
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            price_before: Price::from_sqrt_x96(state.x96price),
+            price_after: Price::from_sqrt_x96(result.x96price),
+            ticks_crossed: result.ticks_crossed,
+            synced_block: self.sync.last_synced_block,
+            checkpoint,
+            trade: UniTrade::V4(result),
+        })
     }
 
     async fn sync(&mut self) -> Result<(), ()> {
@@ -87,7 +105,8 @@ impl<P: Provider> UniPool for V4Pool<P> {
             state.tick = slot0.tick;
 
             if slot0.sqrtPriceX96 != U160::ZERO {
-                return Err(());
+                self.sync.mark_synced(None);
+                return Ok(());
             }
         };
 
@@ -149,9 +168,40 @@ impl<P: Provider> UniPool for V4Pool<P> {
             return Err(());
         }
 
+        self.sync.mark_synced(None);
+        Ok(())
+    }
+
+    fn apply_trade(&mut self, receipt: &TradeReceipt) -> Result<(), StateError> {
+        let UniTrade::V4(trade) = &receipt.trade else {
+            return Err(StateError::WrongPool);
+        };
+        if receipt.pool_id != Some(self.id) {
+            return Err(StateError::WrongPool);
+        }
+        if receipt.checkpoint != self.checkpoint() {
+            return Err(StateError::Changed);
+        }
+        self.state.apply_trade(trade);
         Ok(())
     }
 
+    fn sync_status(&self) -> &SyncStatus {
+        &self.sync
+    }
+
+    fn sync_status_mut(&mut self) -> &mut SyncStatus {
+        &mut self.sync
+    }
+
+    fn checkpoint(&self) -> PoolCheckpoint {
+        self.state.checkpoint()
+    }
+
+    fn rollback(&mut self, checkpoint: PoolCheckpoint) -> Result<(), StateError> {
+        self.state.rollback(checkpoint)
+    }
+
     fn get_a(&self) -> &alloy::primitives::Address {
         &self.key.currency0
     }
@@ -160,8 +210,8 @@ impl<P: Provider> UniPool for V4Pool<P> {
         &self.key.currency1
     }
 
-    fn get_price(&self) -> U256 {
-        self.state.x96price
+    fn get_price(&self) -> Price {
+        Price::from_sqrt_x96(self.state.x96price)
     }
 
     fn get_liquidity(&self) -> U256 {